@@ -0,0 +1,292 @@
+// 1a. audio.rs - loudness measurement and level matching
+// 1b. uses ffmpeg's loudnorm filter in analysis mode (EBU R128)
+// 1c. anything that needs to know how loud something is goes through here
+// 1d. also builds the mastering chain that runs on the final mix
+// 1e. and the music bed that sits under the voice
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use crate::jobs::OutputFormat;
+use crate::presets::ExportPreset;
+use crate::processor::{ProcessResult, ProcessorError};
+
+// 2a. loudness stats for a file
+// integrated/threshold in LUFS, true peak in dBTP, lra in LU
+// silent files come back as -inf, check is_silent before doing math
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Loudness {
+    pub integrated: f64,
+    pub true_peak: f64,
+    pub lra: f64,
+    pub threshold: f64,
+    pub target_offset: f64,
+}
+
+impl Loudness {
+    pub fn is_silent(&self) -> bool {
+        !self.integrated.is_finite()
+    }
+}
+
+// 2b. what the ui gets back about the final mix
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LoudnessReport {
+    pub target_lufs: f64,
+    pub before: Loudness,
+    pub after: Loudness,
+}
+
+// 2c. mastering settings from the job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MasteringOptions {
+    pub enabled: bool,               // off unless the job asks, its three extra passes per format
+    pub target_lufs: Option<f64>,    // None = whatever the platform normalizes to
+    pub true_peak: f64,              // dBTP ceiling, also what the limiter holds
+    pub lra: f64,                    // loudness range target
+    pub noise_reduction: bool,       // afftdn on the voice, for noisy rooms
+    pub highpass_hz: Option<f64>,    // cut rumble under the voice, ~80 is a good start
+}
+
+impl Default for MasteringOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_lufs: None,
+            true_peak: -1.0,
+            lra: 11.0,
+            noise_reduction: false,
+            highpass_hz: None,
+        }
+    }
+}
+
+impl MasteringOptions {
+    // 2d. integrated loudness to master to, the platform's from its preset unless the job says
+    // anything louder just gets turned down by the app, so no point going hotter
+    pub fn target_for(&self, format: &OutputFormat) -> f64 {
+        self.target_lufs.unwrap_or_else(|| ExportPreset::for_format(format).loudness_lufs)
+    }
+
+    // 2e. cleanup for the talking head alone, goes on the user audio in the composite pass
+    // before sfx/music get mixed in, so it never eats the low end of a boom or the bed
+    pub fn voice_filters(&self) -> Option<String> {
+        let mut filters = Vec::new();
+        if let Some(hz) = self.highpass_hz {
+            filters.push(format!("highpass=f={:.0}", hz.max(1.0)));
+        }
+        if self.noise_reduction {
+            filters.push("afftdn=nf=-25".to_string());
+        }
+
+        if filters.is_empty() {
+            None
+        } else {
+            Some(filters.join(","))
+        }
+    }
+
+    // 2f. second pass chain on the final mix: linear loudnorm with pass one stats -> limiter
+    // linear mode keeps the dynamics, loudnorm falls back to dynamic itself if it has to
+    // the limiter is a safety net for the true peak on the resampled output
+    pub fn mastering_filter(&self, measured: &Loudness, target: f64) -> String {
+        let chain = [
+            format!(
+                "loudnorm=I={:.1}:TP={:.1}:LRA={:.1}:measured_I={:.2}:measured_TP={:.2}:\
+                 measured_LRA={:.2}:measured_thresh={:.2}:offset={:.2}:linear=true",
+                target, self.true_peak, self.lra,
+                measured.integrated, measured.true_peak, measured.lra, measured.threshold, measured.target_offset
+            ),
+            "aresample=48000".to_string(),
+            format!("alimiter=limit={:.4}:level=disabled", 10f64.powf(self.true_peak / 20.0)),
+        ];
+
+        chain.join(",")
+    }
+}
+
+// 2g. background music under the whole video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MusicBed {
+    pub path: String,
+    pub level_db: f64,    // bed gain, negative = quieter than the file
+    pub duck_db: f64,     // how far it drops while someone is talking
+    pub fade_in: f64,
+    pub fade_out: f64,
+    pub loop_track: bool, // repeat short tracks to cover the whole video
+}
+
+impl Default for MusicBed {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            level_db: -18.0,
+            duck_db: 8.0,
+            fade_in: 1.0,
+            fade_out: 2.0,
+            loop_track: true,
+        }
+    }
+}
+
+impl MusicBed {
+    // 2h. bed chain for input `index`, ends in [bed]
+    // trim to the video, gain, fades. looping is done with -stream_loop on the input
+    pub fn bed_chain(&self, index: usize, duration: f64) -> String {
+        let fade_in = self.fade_in.clamp(0.0, duration / 2.0);
+        let fade_out = self.fade_out.clamp(0.0, duration / 2.0);
+
+        let mut chain = format!(
+            "[{}:a]atrim=0:{:.3},asetpts=PTS-STARTPTS,aformat=sample_fmts=fltp:sample_rates=48000:channel_layouts=stereo,volume={:.1}dB",
+            index, duration, self.level_db
+        );
+        if fade_in > 0.0 {
+            chain.push_str(&format!(",afade=t=in:st=0:d={:.3}", fade_in));
+        }
+        if fade_out > 0.0 {
+            chain.push_str(&format!(",afade=t=out:st={:.3}:d={:.3}", duration - fade_out, fade_out));
+        }
+        chain.push_str("[bed]");
+        chain
+    }
+
+    // 2i. duck [bed] under [sidechain], ends in [ducked]
+    // sidechaincompress takes a ratio, not a dB amount, so work backwards from
+    // normal speech (~-20dBFS) sitting 16dB over the -36dB threshold:
+    // reduction = over * (1 - 1/ratio)
+    pub fn duck_chain(&self) -> String {
+        const OVER_DB: f64 = 16.0;
+        let duck = self.duck_db.clamp(0.0, OVER_DB - 1.0);
+        let ratio = (1.0 / (1.0 - duck / OVER_DB)).clamp(1.0, 20.0);
+
+        format!(
+            "[bed][sidechain]sidechaincompress=threshold=0.0158:ratio={:.2}:attack=20:release=400[ducked]",
+            ratio
+        )
+    }
+}
+
+// 3a. measure a files loudness
+// first pass of loudnorm, output is thrown away and the stats come out on stderr
+// returns None when the file has no audio at all
+pub async fn measure_loudness(path: &Path) -> ProcessResult<Option<Loudness>> {
+    let output = Command::new("ffmpeg")
+        .args([
+            "-hide_banner",
+            "-nostats",
+            "-i", path.to_str().unwrap(),
+            "-map", "0:a:0?",
+            "-af", "loudnorm=print_format=json",
+            "-f", "null",
+            "-",
+        ])
+        .output()
+        .await?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        log::error!("loudness measurement failed: {}", stderr);
+        return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+    }
+
+    Ok(parse_loudnorm_json(&stderr))
+}
+
+// 3c. pull the stats json out of loudnorm's stderr dump
+// its the last {...} block, everything else is ffmpeg chatter
+pub fn parse_loudnorm_json(stderr: &str) -> Option<Loudness> {
+    let start = stderr.rfind('{')?;
+    let end = start + stderr[start..].find('}')?;
+    let json: serde_json::Value = serde_json::from_str(&stderr[start..=end]).ok()?;
+
+    // loudnorm writes every number as a string, "-inf" included
+    let field = |name: &str| -> Option<f64> {
+        json[name].as_str()?.trim().parse().ok()
+    };
+
+    Some(Loudness {
+        integrated: field("input_i")?,
+        true_peak: field("input_tp")?,
+        lra: field("input_lra")?,
+        threshold: field("input_thresh")?,
+        target_offset: field("target_offset").unwrap_or(0.0),
+    })
+}
+
+// 4a. gain to bring a clip (bumper, music...) level with the main audio
+// matches integrated loudness, but never boosts past +6dB or into a true peak over -1
+// so a quiet intro gets lifted a bit and a loud one always comes down
+pub fn matching_gain_db(clip: &Loudness, reference: &Loudness) -> f64 {
+    if clip.is_silent() || reference.is_silent() {
+        return 0.0;
+    }
+
+    let gain = reference.integrated - clip.integrated;
+    if gain <= 0.0 {
+        return gain;
+    }
+
+    gain.min(6.0).min(-1.0 - clip.true_peak).max(0.0)
+}
+
+// 5a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loudness(integrated: f64, true_peak: f64) -> Loudness {
+        Loudness { integrated, true_peak, lra: 5.0, threshold: integrated - 10.0, target_offset: 0.0 }
+    }
+
+    #[test]
+    fn test_parse_loudnorm_json() {
+        let stderr = "Input #0, mov,mp4 ... \n[Parsed_loudnorm_0 @ 0x5581] \n{\n\t\"input_i\" : \"-23.54\",\n\t\"input_tp\" : \"-4.20\",\n\t\"input_lra\" : \"6.10\",\n\t\"input_thresh\" : \"-34.01\",\n\t\"output_i\" : \"-24.02\",\n\t\"normalization_type\" : \"dynamic\",\n\t\"target_offset\" : \"0.02\"\n}\n";
+        let stats = parse_loudnorm_json(stderr).unwrap();
+        assert_eq!((stats.integrated, stats.true_peak, stats.lra), (-23.54, -4.2, 6.1));
+        assert_eq!(stats.target_offset, 0.02);
+
+        let silent = parse_loudnorm_json("{\"input_i\" : \"-inf\", \"input_tp\" : \"-inf\", \"input_lra\" : \"0.00\", \"input_thresh\" : \"-70.00\"}").unwrap();
+        assert!(silent.is_silent());
+        assert!(parse_loudnorm_json("no audio here").is_none());
+    }
+
+    #[test]
+    fn test_mastering_filter() {
+        let options = MasteringOptions { highpass_hz: Some(80.0), noise_reduction: true, ..MasteringOptions::default() };
+        assert_eq!(options.target_for(&OutputFormat::YouTube), -14.0);
+        assert_eq!(options.target_for(&OutputFormat::Custom), -16.0);
+        let fixed = MasteringOptions { target_lufs: Some(-12.0), ..MasteringOptions::default() };
+        assert_eq!(fixed.target_for(&OutputFormat::TikTok), -12.0);
+
+        let filter = options.mastering_filter(&loudness(-23.5, -4.0), -14.0);
+        assert!(filter.starts_with("loudnorm=I=-14.0:TP=-1.0:LRA=11.0:measured_I=-23.50:"));
+        assert_eq!(options.voice_filters().as_deref(), Some("highpass=f=80,afftdn=nf=-25"));
+        assert!(filter.ends_with("aresample=48000,alimiter=limit=0.8913:level=disabled"));
+
+        assert!(MasteringOptions::default().voice_filters().is_none());
+    }
+
+    #[test]
+    fn test_music_bed_chain() {
+        let bed = MusicBed { path: "music.mp3".to_string(), fade_in: 30.0, ..MusicBed::default() };
+        assert_eq!(
+            bed.bed_chain(1, 20.0),
+            "[1:a]atrim=0:20.000,asetpts=PTS-STARTPTS,aformat=sample_fmts=fltp:sample_rates=48000:channel_layouts=stereo,\
+             volume=-18.0dB,afade=t=in:st=0:d=10.000,afade=t=out:st=18.000:d=2.000[bed]"
+        );
+        assert!(bed.duck_chain().contains("ratio=2.00"));
+    }
+
+    #[test]
+    fn test_matching_gain() {
+        let voice = loudness(-18.0, -2.0);
+        // loud bumper comes all the way down
+        assert_eq!(matching_gain_db(&loudness(-9.0, -0.5), &voice), -9.0);
+        // quiet bumper gets capped by the +6 limit, then by headroom
+        assert_eq!(matching_gain_db(&loudness(-30.0, -12.0), &voice), 6.0);
+        assert_eq!(matching_gain_db(&loudness(-22.0, -2.5), &voice), 1.5);
+        assert_eq!(matching_gain_db(&loudness(f64::NEG_INFINITY, f64::NEG_INFINITY), &voice), 0.0);
+    }
+}
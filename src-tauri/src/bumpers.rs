@@ -0,0 +1,177 @@
+// 1a. bumpers.rs - branded intro/outro clips around the render
+// 1b. bumpers get conformed to the output size/fps first, then joined here
+// 1c. join is either a straight concat or an xfade/acrossfade chain
+
+use serde::{Deserialize, Serialize};
+
+// 2a. transition styles, these map straight onto xfade's names
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum BumperTransitionKind {
+    #[default]
+    Fade,
+    FadeBlack,
+    Dissolve,
+    WipeLeft,
+    SlideLeft,
+}
+
+impl BumperTransitionKind {
+    fn xfade_name(&self) -> &'static str {
+        match self {
+            BumperTransitionKind::Fade => "fade",
+            BumperTransitionKind::FadeBlack => "fadeblack",
+            BumperTransitionKind::Dissolve => "dissolve",
+            BumperTransitionKind::WipeLeft => "wipeleft",
+            BumperTransitionKind::SlideLeft => "slideleft",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BumperTransition {
+    pub kind: BumperTransitionKind,
+    pub duration: f64,  // seconds of overlap
+}
+
+// 2b. bumper settings from the job
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bumpers {
+    pub intro: Option<String>,
+    pub outro: Option<String>,
+    pub transition: Option<BumperTransition>,  // None = hard cut
+}
+
+impl Bumpers {
+    pub fn is_empty(&self) -> bool {
+        self.intro.is_none() && self.outro.is_none()
+    }
+}
+
+// 2c. one input to the join, in order
+#[derive(Debug, Clone, Copy)]
+pub struct JoinSegment {
+    pub duration: f64,
+    pub has_audio: bool,
+}
+
+// 3a. transition length that actually fits
+// xfade needs a positive offset, so it cant be longer than half the shortest segment
+pub fn transition_duration(segments: &[JoinSegment], transition: Option<&BumperTransition>) -> f64 {
+    let shortest = segments.iter().map(|s| s.duration).fold(f64::INFINITY, f64::min);
+    match transition {
+        Some(t) if segments.len() > 1 => t.duration.clamp(0.0, shortest / 2.0),
+        _ => 0.0,
+    }
+}
+
+// 3b. where segment `index` starts in the joined video
+// captions/sfx timed against the main render get shifted by this
+pub fn segment_offset(segments: &[JoinSegment], index: usize, transition: Option<&BumperTransition>) -> f64 {
+    let overlap = transition_duration(segments, transition);
+    segments[..index].iter().map(|s| s.duration - overlap).sum()
+}
+
+// 4a. filter graph joining the inputs [0..n) in order
+// every input gets the same fps/sar/pixel format/audio layout so
+// concat and xfade dont complain, silent inputs get a null audio track
+// result is [vout] and [aout]
+pub fn join_graph(segments: &[JoinSegment], fps: f64, transition: Option<&BumperTransition>) -> String {
+    let mut parts = Vec::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        parts.push(format!(
+            "[{}:v]fps={:.3},setsar=1,format=yuv420p,settb=AVTB[v{}]",
+            i, fps, i
+        ));
+        if segment.has_audio {
+            parts.push(format!(
+                "[{}:a]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[a{}]",
+                i, i
+            ));
+        } else {
+            parts.push(format!(
+                "anullsrc=r=48000:cl=stereo,atrim=duration={:.3},aformat=sample_fmts=fltp[a{}]",
+                segment.duration, i
+            ));
+        }
+    }
+
+    let overlap = transition_duration(segments, transition);
+
+    match transition {
+        Some(t) if overlap > 0.0 => {
+            // chain xfades, each offset is the joined length so far minus the overlap
+            let mut video = "v0".to_string();
+            let mut audio = "a0".to_string();
+            let mut length = segments[0].duration;
+
+            for (i, segment) in segments.iter().enumerate().skip(1) {
+                let last = i == segments.len() - 1;
+                let next_video = if last { "vout".to_string() } else { format!("vx{}", i) };
+                let next_audio = if last { "aout".to_string() } else { format!("ax{}", i) };
+
+                parts.push(format!(
+                    "[{}][v{}]xfade=transition={}:duration={:.3}:offset={:.3}[{}]",
+                    video, i, t.kind.xfade_name(), overlap, length - overlap, next_video
+                ));
+                parts.push(format!(
+                    "[{}][a{}]acrossfade=d={:.3}[{}]",
+                    audio, i, overlap, next_audio
+                ));
+
+                length += segment.duration - overlap;
+                video = next_video;
+                audio = next_audio;
+            }
+        }
+        _ => {
+            let inputs: String = (0..segments.len())
+                .map(|i| format!("[v{}][a{}]", i, i))
+                .collect();
+            parts.push(format!(
+                "{}concat=n={}:v=1:a=1[vout][aout]",
+                inputs,
+                segments.len()
+            ));
+        }
+    }
+
+    parts.join(";")
+}
+
+// 5a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments() -> Vec<JoinSegment> {
+        vec![
+            JoinSegment { duration: 3.0, has_audio: true },
+            JoinSegment { duration: 20.0, has_audio: false },
+            JoinSegment { duration: 4.0, has_audio: true },
+        ]
+    }
+
+    #[test]
+    fn test_hard_cut_concat() {
+        let graph = join_graph(&segments(), 30.0, None);
+        assert!(graph.contains("anullsrc=r=48000:cl=stereo,atrim=duration=20.000,aformat=sample_fmts=fltp[a1]"));
+        assert!(graph.ends_with("[v0][a0][v1][a1][v2][a2]concat=n=3:v=1:a=1[vout][aout]"));
+        assert_eq!(segment_offset(&segments(), 1, None), 3.0);
+    }
+
+    #[test]
+    fn test_xfade_chain() {
+        let fade = BumperTransition { kind: BumperTransitionKind::FadeBlack, duration: 0.5 };
+        let graph = join_graph(&segments(), 30.0, Some(&fade));
+        assert!(graph.contains("[v0][v1]xfade=transition=fadeblack:duration=0.500:offset=2.500[vx1]"));
+        assert!(graph.contains("[vx1][v2]xfade=transition=fadeblack:duration=0.500:offset=22.000[vout]"));
+        assert!(graph.contains("[ax1][a2]acrossfade=d=0.500[aout]"));
+        assert_eq!(segment_offset(&segments(), 1, Some(&fade)), 2.5);
+
+        // too long for a 3s intro, gets clamped to half of it
+        let slow = BumperTransition { kind: BumperTransitionKind::Fade, duration: 5.0 };
+        assert_eq!(transition_duration(&segments(), Some(&slow)), 1.5);
+    }
+}
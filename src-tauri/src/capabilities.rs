@@ -0,0 +1,254 @@
+// 1a. capabilities.rs - what the installed ffmpeg can actually do
+// 1b. versions, encoders, filters and hwaccels, probed once at startup and shared through app state
+// 1c. distro builds leave stuff out (no libass = no subtitles filter, no freetype = no drawtext)
+// so jobs get checked against this instead of finding out mid-render
+
+use serde::Serialize;
+use crate::hwaccel::{self, HwEncoder};
+use crate::jobs::JobConfig;
+use crate::processor::GpuEncoder;
+
+// 2a. filters features depend on, shown in the dependency status when missing
+pub const KEY_FILTERS: &[&str] = &[
+    "xfade", "loudnorm", "subtitles", "drawtext",
+    "amix", "asplit", "alimiter", "sidechaincompress", "afftdn", "highpass",
+];
+
+// 2b. the probe result
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub ffmpeg_version: Option<String>,   // None = not installed / wont run
+    pub ffprobe_version: Option<String>,
+    pub encoders: Vec<String>,
+    pub filters: Vec<String>,
+    pub hwaccels: Vec<String>,
+    #[serde(skip)]
+    pub hw_encoder: HwEncoder,            // the hw encoder that passed a test encode
+}
+
+impl Capabilities {
+    // 3b. run everything once
+    pub fn probe() -> Self {
+        let ffmpeg_version = run("ffmpeg", &["-hide_banner", "-version"]).and_then(|out| parse_version(&out));
+        let ffprobe_version = run("ffprobe", &["-hide_banner", "-version"]).and_then(|out| parse_version(&out));
+        if ffmpeg_version.is_none() {
+            return Self::missing();
+        }
+
+        let encoders = run("ffmpeg", &["-hide_banner", "-encoders"]).map(|out| parse_encoders(&out)).unwrap_or_default();
+        let filters = run("ffmpeg", &["-hide_banner", "-filters"]).map(|out| parse_filters(&out)).unwrap_or_default();
+        let hwaccels = run("ffmpeg", &["-hide_banner", "-hwaccels"]).map(|out| parse_hwaccels(&out)).unwrap_or_default();
+        let hw_encoder = hwaccel::probe(&encoders);
+
+        let caps = Self { ffmpeg_version, ffprobe_version, encoders, filters, hwaccels, hw_encoder };
+        log::info!(
+            "ffmpeg {} - {} encoders, {} filters, hwaccels [{}], using {:?}",
+            caps.ffmpeg_version.as_deref().unwrap_or("?"),
+            caps.encoders.len(),
+            caps.filters.len(),
+            caps.hwaccels.join(", "),
+            caps.hw_encoder.kind
+        );
+        for filter in caps.missing_filters() {
+            log::warn!("ffmpeg has no {} filter, features that need it are off", filter);
+        }
+        caps
+    }
+
+    // what jobs see before the probe finished or when ffmpeg isnt there
+    pub fn missing() -> Self {
+        Self {
+            ffmpeg_version: None,
+            ffprobe_version: None,
+            encoders: Vec::new(),
+            filters: Vec::new(),
+            hwaccels: Vec::new(),
+            hw_encoder: HwEncoder { kind: GpuEncoder::None, vaapi_device: None },
+        }
+    }
+
+    // 4a. lookups
+    pub fn ffmpeg_installed(&self) -> bool {
+        self.ffmpeg_version.is_some() && self.ffprobe_version.is_some()
+    }
+
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.encoders.iter().any(|e| e == name)
+    }
+
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters.iter().any(|f| f == name)
+    }
+
+    pub fn missing_filters(&self) -> Vec<&'static str> {
+        KEY_FILTERS.iter().copied().filter(|f| !self.has_filter(f)).collect()
+    }
+
+    // 4b. encoder name for the status bar
+    pub fn gpu_encoder_name(&self) -> String {
+        match self.hw_encoder.kind {
+            GpuEncoder::Nvenc => "NVIDIA NVENC (GPU)",
+            GpuEncoder::Vaapi => "AMD/Intel VAAPI (GPU)",
+            GpuEncoder::Videotoolbox => "Apple VideoToolbox (GPU)",
+            GpuEncoder::None => "CPU (libx264)",
+        }.to_string()
+    }
+
+    // 5a. what a job asks for that this ffmpeg cant do
+    // the cpu encoder has to be there even with a gpu, its what a failed hw encode falls back to
+    pub fn unsupported(&self, config: &JobConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+        // one message per missing filter, even when a few features need it
+        let mut need = |filter: &str, feature: &str| {
+            let named = format!("the {} filter", filter);
+            if !self.has_filter(filter) && !problems.iter().any(|p: &String| p.contains(&named)) {
+                problems.push(format!("{} needs the {} filter, your ffmpeg was built without it", feature, filter));
+            }
+        };
+
+        let burned_captions = config.captions.as_ref().is_some_and(|c| c.burn_in);
+        if burned_captions || config.subtitles_path.is_some() {
+            need("subtitles", "burned in captions");
+        }
+        if config.title.as_deref().is_some_and(|t| !t.trim().is_empty()) || !config.text_overlays.is_empty() {
+            need("drawtext", "text overlays");
+        }
+        if config.mastering.enabled {
+            need("loudnorm", "audio mastering");
+            need("alimiter", "audio mastering");
+            if config.mastering.highpass_hz.is_some() {
+                need("highpass", "the voice high-pass");
+            }
+            if config.mastering.noise_reduction {
+                need("afftdn", "noise reduction");
+            }
+        }
+        // sfx + music go through one amix pass with a limiter on the end
+        let sfx = config.sfx_cues.is_some() || (config.sfx_folder.is_some() && config.sfx_placement.enabled);
+        if sfx || config.music.is_some() {
+            need("amix", "sound effects and music");
+            need("asplit", "sound effects and music");
+            need("alimiter", "sound effects and music");
+        }
+        if config.music.is_some() {
+            need("sidechaincompress", "music ducking");
+        }
+        if config.bumpers.as_ref().is_some_and(|b| !b.is_empty() && b.transition.is_some()) {
+            need("xfade", "bumper transitions");
+        }
+
+        for format in &config.output_formats {
+            let encoder = config.encoder_for(format).encoder_name(GpuEncoder::None);
+            if !self.has_encoder(encoder) && !problems.iter().any(|p| p.contains(encoder)) {
+                problems.push(format!("{} isnt in your ffmpeg, pick another codec", encoder));
+            }
+        }
+        problems
+    }
+}
+
+// 6a. run a probe command, None if it wont start or fails
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// 6b. "ffmpeg version 6.1.1-3ubuntu5 Copyright ..." -> "6.1.1-3ubuntu5"
+fn parse_version(output: &str) -> Option<String> {
+    let first = output.lines().next()?;
+    let mut words = first.split_whitespace();
+    words.find(|w| *w == "version")?;
+    words.next().map(str::to_string)
+}
+
+// 6c. encoder list, the legend above the ------ line gets skipped
+fn parse_encoders(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1).map(str::to_string))
+        .collect()
+}
+
+// 6d. filter list has no separator, real rows have an "A->V" style io column
+fn parse_filters(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [_, name, io, ..] if io.contains("->") => Some(name.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// 6e. one hwaccel per line after the heading
+fn parse_hwaccels(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// 7a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{MasteringOptions, MusicBed};
+    use crate::jobs::JobConfig;
+
+    #[test]
+    fn test_parses_ffmpeg_listings() {
+        assert_eq!(parse_version("ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023\n").as_deref(), Some("6.1.1-3ubuntu5"));
+        assert_eq!(parse_version("bash: ffmpeg: command not found"), None);
+
+        let encoders = " V..... = Video\n A..... = Audio\n ------\n V....D libx264               libx264 H.264\n V....D h264_nvenc           NVIDIA NVENC\n A....D aac                  AAC\n";
+        assert_eq!(parse_encoders(encoders), ["libx264", "h264_nvenc", "aac"]);
+
+        let filters = "Filters:\n  T.. = Timeline support\n  A = Audio input/output\n TSC loudnorm           A->A       EBU R128\n ... xfade             VV->V      Cross fade\n";
+        assert_eq!(parse_filters(filters), ["loudnorm", "xfade"]);
+
+        assert_eq!(parse_hwaccels("Hardware acceleration methods:\nvdpau\ncuda\nvaapi\n\n"), ["vdpau", "cuda", "vaapi"]);
+    }
+
+    #[test]
+    fn test_unsupported_names_what_is_missing() {
+        let caps = Capabilities {
+            ffmpeg_version: Some("6.1".to_string()),
+            ffprobe_version: Some("6.1".to_string()),
+            encoders: vec!["libx264".to_string(), "aac".to_string()],
+            filters: ["loudnorm", "xfade", "subtitles", "amix", "asplit", "alimiter", "highpass"]
+                .iter().map(|f| f.to_string()).collect(),
+            ..Capabilities::missing()
+        };
+        assert_eq!(caps.missing_filters(), ["drawtext", "sidechaincompress", "afftdn"]);
+
+        let plain = JobConfig::default();
+        assert!(caps.unsupported(&plain).is_empty());
+
+        let titled = JobConfig { title: Some("day 3".to_string()), ..JobConfig::default() };
+        let problems = caps.unsupported(&titled);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("drawtext"));
+
+        // music needs the sidechain, denoise needs afftdn - highpass is there so thats fine
+        let mixed = JobConfig {
+            music: Some(MusicBed::default()),
+            mastering: MasteringOptions { enabled: true, highpass_hz: Some(80.0), noise_reduction: true, ..MasteringOptions::default() },
+            ..JobConfig::default()
+        };
+        let problems = caps.unsupported(&mixed);
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().any(|p| p.contains("sidechaincompress")));
+        assert!(problems.iter().any(|p| p.contains("afftdn")));
+    }
+}
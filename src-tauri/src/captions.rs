@@ -0,0 +1,562 @@
+// 1a. captions.rs - speech to text captions for the user video
+// 1b. runs whisper.cpp locally on the cpu, gets word level timestamps
+// 1c. spits out srt/vtt sidecars and an .ass file for burning in
+// shelling out like we do for ffmpeg and yt-dlp, the rust bindings need a c++ toolchain
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::process::Command;
+use crate::jobs::OverlayPosition;
+use crate::processor::{Dimensions, Processor};
+use crate::setup;
+
+// 2a. stuff that can go wrong while captioning
+#[derive(Error, Debug)]
+pub enum CaptionError {
+    #[error("whisper.cpp not found - install it (brew install whisper-cpp) or put whisper-cli in the godz bin folder")]
+    WhisperNotFound,
+
+    #[error("transcription failed: {0}")]
+    TranscriptionFailed(String),
+
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+pub type CaptionResult<T> = Result<T, CaptionError>;
+
+// 3a. a single spoken word with timing (seconds)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptionWord {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+// 3b. one caption on screen
+// words is empty when we only know the cue timing (imported subs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptionCue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub words: Vec<CaptionWord>,
+}
+
+// 3c. where captions hang relative to the layout
+// the split variants only mean something for top/bottom splits,
+// other layouts treat them like Auto
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum CaptionAnchor {
+    #[default]
+    Auto,        // layout picks (split line for splits, bottom safe area otherwise)
+    AboveSplit,  // bottom edge of the text sits on the split line
+    OnSplit,     // centered on the split line
+    BelowSplit,  // top edge of the text sits on the split line
+    Top,
+    Bottom,
+}
+
+// 3d. how burned in captions look
+// colors are "#rrggbb"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptionStyle {
+    pub font: String,
+    pub font_size: u32,
+    pub color: String,
+    pub highlight_color: String,  // the word being spoken right now
+    pub outline_color: String,
+    pub outline_width: f64,
+    pub bold: bool,
+    pub uppercase: bool,
+    pub highlight_words: bool,
+    pub anchor: CaptionAnchor,
+    pub offset: i32,  // pixels away from the anchor line
+}
+
+impl Default for CaptionStyle {
+    fn default() -> Self {
+        Self {
+            font: "Arial".to_string(),
+            font_size: 64,
+            color: "#ffffff".to_string(),
+            highlight_color: "#ffe000".to_string(),
+            outline_color: "#000000".to_string(),
+            outline_width: 4.0,
+            bold: true,
+            uppercase: false,
+            highlight_words: true,
+            anchor: CaptionAnchor::Auto,
+            offset: 20,
+        }
+    }
+}
+
+// 3e. everything a job can set about captions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptionOptions {
+    pub burn_in: bool,
+    pub export_srt: bool,
+    pub export_vtt: bool,
+    pub style: CaptionStyle,
+    pub model: String,             // ggml model name ("base", "small.en") or a path to one
+    pub language: Option<String>,  // None = let whisper guess
+    pub max_words_per_cue: usize,
+    pub max_cue_duration: f64,
+}
+
+impl Default for CaptionOptions {
+    fn default() -> Self {
+        Self {
+            burn_in: true,
+            export_srt: true,
+            export_vtt: false,
+            style: CaptionStyle::default(),
+            model: "base".to_string(),
+            language: None,
+            max_words_per_cue: 4,  // short chunks read better on vertical video
+            max_cue_duration: 2.5,
+        }
+    }
+}
+
+// 4a. transcribe the user video into words
+// audio gets pulled to 16khz mono wav first cuz thats what whisper eats
+pub async fn transcribe(
+    processor: &Processor,
+    video_path: &Path,
+    work_dir: &Path,
+    options: &CaptionOptions,
+) -> CaptionResult<Vec<CaptionWord>> {
+    let whisper = setup::find_whisper_binary().ok_or(CaptionError::WhisperNotFound)?;
+
+    // model can be a straight path or a name we download
+    let model_path = if Path::new(&options.model).exists() {
+        PathBuf::from(&options.model)
+    } else {
+        setup::ensure_whisper_model(&options.model)
+            .await
+            .map_err(CaptionError::TranscriptionFailed)?
+    };
+
+    std::fs::create_dir_all(work_dir)?;
+    let wav_path = work_dir.join("captions_audio.wav");
+    processor
+        .extract_audio(video_path, &wav_path, 16000)
+        .await
+        .map_err(|e| CaptionError::TranscriptionFailed(e.to_string()))?;
+
+    // -ml 1 + -sow gives one segment per word, -oj writes <base>.json
+    let out_base = work_dir.join("captions");
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get().min(8))
+        .unwrap_or(4);
+
+    let output = Command::new(&whisper)
+        .args([
+            "-m", model_path.to_str().unwrap(),
+            "-f", wav_path.to_str().unwrap(),
+            "-l", options.language.as_deref().unwrap_or("auto"),
+            "-t", &threads.to_string(),
+            "-ml", "1",
+            "-sow",
+            "-oj",
+            "-np",
+            "-of", out_base.to_str().unwrap(),
+        ])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::error!("whisper failed: {}", stderr);
+        return Err(CaptionError::TranscriptionFailed(stderr.to_string()));
+    }
+
+    let json = std::fs::read_to_string(out_base.with_extension("json"))?;
+    let words = parse_whisper_json(&json)?;
+    log::info!("transcribed {} words", words.len());
+
+    Ok(words)
+}
+
+// 4b. pull words out of whisper.cpp's json output
+// offsets are in milliseconds, text has a leading space
+// skips the [BLANK_AUDIO] style markers
+fn parse_whisper_json(json: &str) -> CaptionResult<Vec<CaptionWord>> {
+    let value: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| CaptionError::TranscriptionFailed(e.to_string()))?;
+
+    let segments = value["transcription"]
+        .as_array()
+        .ok_or_else(|| CaptionError::TranscriptionFailed("no transcription in whisper output".to_string()))?;
+
+    let words = segments
+        .iter()
+        .filter_map(|seg| {
+            let text = seg["text"].as_str()?.trim();
+            if text.is_empty() || (text.starts_with('[') && text.ends_with(']')) {
+                return None;
+            }
+            Some(CaptionWord {
+                text: text.to_string(),
+                start: seg["offsets"]["from"].as_f64()? / 1000.0,
+                end: seg["offsets"]["to"].as_f64()? / 1000.0,
+            })
+        })
+        .collect();
+
+    Ok(words)
+}
+
+// 5a. chunk words into on-screen cues
+// breaks on word count, cue length, or a real pause in speech
+pub fn group_words(words: &[CaptionWord], max_words: usize, max_duration: f64) -> Vec<CaptionCue> {
+    let max_words = max_words.max(1);
+    let mut cues = Vec::new();
+    let mut current: Vec<CaptionWord> = Vec::new();
+
+    for word in words {
+        let should_break = match (current.first(), current.last()) {
+            (Some(first), Some(last)) => {
+                current.len() >= max_words
+                    || word.end - first.start > max_duration
+                    || word.start - last.end > 0.6
+            }
+            _ => false,
+        };
+
+        if should_break {
+            cues.push(cue_from_words(std::mem::take(&mut current)));
+        }
+        current.push(word.clone());
+    }
+
+    if !current.is_empty() {
+        cues.push(cue_from_words(current));
+    }
+
+    cues
+}
+
+// 5b. push cues later in time, for when an intro gets stuck in front
+pub fn shift_cues(cues: &[CaptionCue], offset: f64) -> Vec<CaptionCue> {
+    cues.iter()
+        .map(|cue| CaptionCue {
+            start: cue.start + offset,
+            end: cue.end + offset,
+            text: cue.text.clone(),
+            words: cue.words.iter()
+                .map(|w| CaptionWord { text: w.text.clone(), start: w.start + offset, end: w.end + offset })
+                .collect(),
+        })
+        .collect()
+}
+
+fn cue_from_words(words: Vec<CaptionWord>) -> CaptionCue {
+    CaptionCue {
+        start: words[0].start,
+        end: words[words.len() - 1].end,
+        text: words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" "),
+        words,
+    }
+}
+
+// 6a. srt export
+pub fn to_srt(cues: &[CaptionCue]) -> String {
+    cues.iter()
+        .enumerate()
+        .map(|(i, cue)| format!(
+            "{}\n{} --> {}\n{}\n",
+            i + 1,
+            format_timestamp(cue.start, ','),
+            format_timestamp(cue.end, ','),
+            cue.text
+        ))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 6b. webvtt export
+pub fn to_vtt(cues: &[CaptionCue]) -> String {
+    let body: String = cues
+        .iter()
+        .map(|cue| format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(cue.start, '.'),
+            format_timestamp(cue.end, '.'),
+            cue.text
+        ))
+        .collect();
+    format!("WEBVTT\n\n{}", body)
+}
+
+// 6c. write the sidecar files next to the rendered video
+// returns whatever actually got written
+pub fn write_sidecars(cues: &[CaptionCue], video_path: &Path, srt: bool, vtt: bool) -> std::io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+
+    if srt {
+        let path = video_path.with_extension("srt");
+        std::fs::write(&path, to_srt(cues))?;
+        written.push(path);
+    }
+    if vtt {
+        let path = video_path.with_extension("vtt");
+        std::fs::write(&path, to_vtt(cues))?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+// hh:mm:ss,mmm (srt) or hh:mm:ss.mmm (vtt)
+fn format_timestamp(seconds: f64, separator: char) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        total_ms / 3_600_000,
+        (total_ms / 60_000) % 60,
+        (total_ms / 1000) % 60,
+        separator,
+        total_ms % 1000
+    )
+}
+
+// 7a. where captions sit on screen
+// x/y is the anchor point in output pixels, alignment is the ass numpad code
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptionPlacement {
+    pub x: u32,
+    pub y: u32,
+    pub alignment: u8,
+}
+
+impl CaptionPlacement {
+    // 7b. pick a spot that works for the layout
+    // splits: centered on the split line, thats where eyes already are
+    // everything else: bottom center, kept out of the platform ui
+    // (vertical apps cover roughly the bottom fifth with buttons and descriptions)
+    pub fn for_layout(position: OverlayPosition, target: Dimensions, split_ratio: f64) -> Self {
+        let bottom_y = auto_bottom(target) as u32;
+
+        match position {
+            OverlayPosition::Top => Self {
+                x: target.width / 2,
+                y: (target.height as f64 * split_ratio) as u32,
+                alignment: 5,
+            },
+            OverlayPosition::Bottom => Self {
+                x: target.width / 2,
+                y: (target.height as f64 * (1.0 - split_ratio)) as u32,
+                alignment: 5,
+            },
+            OverlayPosition::SideBySide => {
+                // center under the user half on the right
+                let left_width = (target.width as f64 * split_ratio) as u32;
+                Self {
+                    x: left_width + (target.width - left_width) / 2,
+                    y: bottom_y,
+                    alignment: 2,
+                }
+            }
+            _ => Self {
+                x: target.width / 2,
+                y: bottom_y,
+                alignment: 2,
+            },
+        }
+    }
+
+    // 7c. same thing but honoring the style's anchor + offset
+    pub fn for_style(style: &CaptionStyle, position: OverlayPosition, target: Dimensions, split_ratio: f64) -> Self {
+        let auto = Self::for_layout(position, target, split_ratio);
+        let center_x = target.width / 2;
+        let offset = style.offset;
+        let at = |y: i64, alignment: u8| Self {
+            x: center_x,
+            y: y.clamp(0, target.height as i64) as u32,
+            alignment,
+        };
+
+        let split_y = match position {
+            OverlayPosition::Top => Some((target.height as f64 * split_ratio) as i64),
+            OverlayPosition::Bottom => Some((target.height as f64 * (1.0 - split_ratio)) as i64),
+            _ => None,
+        };
+
+        match (style.anchor, split_y) {
+            (CaptionAnchor::AboveSplit, Some(y)) => at(y - offset as i64, 2),
+            (CaptionAnchor::OnSplit, Some(y)) => at(y + offset as i64, 5),
+            (CaptionAnchor::BelowSplit, Some(y)) => at(y + offset as i64, 8),
+            (CaptionAnchor::Top, _) => at((target.height as f64 * 0.08) as i64 + offset as i64, 8),
+            (CaptionAnchor::Bottom, _) => Self { x: auto.x, ..at(auto_bottom(target) - offset as i64, 2) },
+            _ => auto,
+        }
+    }
+}
+
+// bottom of the safe area, vertical apps cover more of the bottom
+fn auto_bottom(target: Dimensions) -> i64 {
+    let safe_bottom = if target.height > target.width { 0.2 } else { 0.08 };
+    (target.height as f64 * (1.0 - safe_bottom)) as i64
+}
+
+// 8a. build an .ass subtitle script for burning in
+// word highlight = one event per spoken word with that word recolored
+pub fn to_ass(cues: &[CaptionCue], style: &CaptionStyle, placement: CaptionPlacement, target: Dimensions) -> String {
+    let mut script = format!(
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {}\n\
+         PlayResY: {}\n\
+         WrapStyle: 0\n\
+         ScaledBorderAndShadow: yes\n\n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Default,{},{},{},{},{},&H80000000,{},0,0,0,100,100,0,0,1,{:.1},0,{},40,40,40,1\n\n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        target.width, target.height,
+        style.font, style.font_size,
+        ass_color(&style.color), ass_color(&style.highlight_color), ass_color(&style.outline_color),
+        if style.bold { -1 } else { 0 },
+        style.outline_width,
+        placement.alignment
+    );
+
+    let prefix = format!("{{\\an{}\\pos({},{})}}", placement.alignment, placement.x, placement.y);
+    let highlight = ass_color(&style.highlight_color);
+    let fix_case = |text: &str| if style.uppercase { text.to_uppercase() } else { text.to_string() };
+
+    for cue in cues {
+        if style.highlight_words && !cue.words.is_empty() {
+            for (i, word) in cue.words.iter().enumerate() {
+                // each word stays lit until the next one starts so there are no gaps
+                let end = cue.words.get(i + 1).map(|w| w.start).unwrap_or(cue.end);
+                let text: Vec<String> = cue.words
+                    .iter()
+                    .enumerate()
+                    .map(|(j, w)| {
+                        let escaped = escape_ass_text(&fix_case(&w.text));
+                        if i == j {
+                            format!("{{\\c{}}}{}{{\\r}}", highlight, escaped)
+                        } else {
+                            escaped
+                        }
+                    })
+                    .collect();
+                script.push_str(&dialogue_line(word.start, end, &prefix, &text.join(" ")));
+            }
+        } else {
+            script.push_str(&dialogue_line(cue.start, cue.end, &prefix, &escape_ass_text(&fix_case(&cue.text))));
+        }
+    }
+
+    script
+}
+
+fn dialogue_line(start: f64, end: f64, prefix: &str, text: &str) -> String {
+    format!(
+        "Dialogue: 0,{},{},Default,,0,0,0,,{}{}\n",
+        format_ass_timestamp(start),
+        format_ass_timestamp(end),
+        prefix,
+        text
+    )
+}
+
+// ass wants h:mm:ss.cc (centiseconds)
+fn format_ass_timestamp(seconds: f64) -> String {
+    let total_cs = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        total_cs / 360_000,
+        (total_cs / 6000) % 60,
+        (total_cs / 100) % 60,
+        total_cs % 100
+    )
+}
+
+// "#rrggbb" -> ass "&H00bbggrr", bad input falls back to white
+fn ass_color(hex: &str) -> String {
+    let hex = hex.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return "&H00FFFFFF".to_string();
+    }
+    format!("&H00{}{}{}", &hex[4..6], &hex[2..4], &hex[0..2]).to_uppercase()
+}
+
+// braces start override blocks and backslashes start tags, so neuter both
+fn escape_ass_text(text: &str) -> String {
+    text.replace('\\', "/")
+        .replace('{', "(")
+        .replace('}', ")")
+        .replace('\n', "\\N")
+}
+
+// 9a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, start: f64, end: f64) -> CaptionWord {
+        CaptionWord { text: text.to_string(), start, end }
+    }
+
+    #[test]
+    fn test_parse_whisper_json() {
+        let json = r#"{"transcription":[
+            {"offsets":{"from":0,"to":420},"text":" Hey"},
+            {"offsets":{"from":420,"to":900},"text":" [BLANK_AUDIO]"},
+            {"offsets":{"from":900,"to":1300},"text":" guys"}
+        ]}"#;
+        let words = parse_whisper_json(json).unwrap();
+        assert_eq!(words, vec![word("Hey", 0.0, 0.42), word("guys", 0.9, 1.3)]);
+    }
+
+    #[test]
+    fn test_group_words_breaks_on_pause_and_count() {
+        let words = vec![
+            word("one", 0.0, 0.3),
+            word("two", 0.3, 0.6),
+            word("three", 0.6, 0.9),
+            word("four", 2.0, 2.3),
+        ];
+        let cues = group_words(&words, 2, 5.0);
+        let texts: Vec<_> = cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["one two", "three", "four"]);
+    }
+
+    #[test]
+    fn test_srt_and_vtt_output() {
+        let cues = group_words(&[word("hello", 61.5, 62.25)], 4, 3.0);
+        assert_eq!(to_srt(&cues), "1\n00:01:01,500 --> 00:01:02,250\nhello\n");
+        assert_eq!(to_vtt(&cues), "WEBVTT\n\n00:01:01.500 --> 00:01:02.250\nhello\n\n");
+    }
+
+    #[test]
+    fn test_ass_word_highlight() {
+        let cues = group_words(&[word("big", 0.0, 0.5), word("news", 0.5, 1.0)], 4, 3.0);
+        let placement = CaptionPlacement::for_layout(OverlayPosition::Top, Dimensions::tiktok(), 0.5);
+        assert_eq!(placement, CaptionPlacement { x: 540, y: 960, alignment: 5 });
+
+        let ass = to_ass(&cues, &CaptionStyle::default(), placement, Dimensions::tiktok());
+        assert!(ass.contains("Dialogue: 0,0:00:00.00,0:00:00.50,Default,,0,0,0,,{\\an5\\pos(540,960)}{\\c&H0000E0FF}big{\\r} news\n"));
+        assert!(ass.contains("Dialogue: 0,0:00:00.50,0:00:01.00,Default,,0,0,0,,{\\an5\\pos(540,960)}big {\\c&H0000E0FF}news{\\r}\n"));
+    }
+
+    #[test]
+    fn test_anchor_relative_to_split() {
+        let style = CaptionStyle { anchor: CaptionAnchor::AboveSplit, offset: 30, ..CaptionStyle::default() };
+        let placement = CaptionPlacement::for_style(&style, OverlayPosition::Bottom, Dimensions::tiktok(), 0.6);
+        assert_eq!(placement, CaptionPlacement { x: 540, y: 738, alignment: 2 });
+
+        // no split line in pip, falls back to the layout default
+        let placement = CaptionPlacement::for_style(&style, OverlayPosition::TopLeft, Dimensions::tiktok(), 0.6);
+        assert_eq!(placement, CaptionPlacement::for_layout(OverlayPosition::TopLeft, Dimensions::tiktok(), 0.6));
+    }
+}
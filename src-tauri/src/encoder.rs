@@ -0,0 +1,341 @@
+// 1a. encoder.rs - encoder profiles
+// 1b. codec + quality preset + rate control, picked per job
+// 1c. turns a profile into ffmpeg args for whichever backend the processor found
+// every profile works on the cpu path, gpus only get codecs they can actually do
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::processor::GpuEncoder;
+
+// 2a. output codecs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    Hevc,
+    Av1,   // libsvtav1 on cpu
+    Vp9,
+}
+
+// 2b. speed vs quality, maps to each encoder's preset + default quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QualityPreset {
+    Draft,      // quick look, big fast presets
+    #[default]
+    Standard,   // what godz always did
+    High,       // slow presets, low crf
+    Preview,    // fastest thing each encoder has, only for layout previews
+}
+
+// 2c. the whole profile
+// crf is in the encoder's own scale (crf for cpu, cq/qp/q:v on gpus)
+// bitrate_kbps set = target bitrate instead of constant quality
+// target_size_mb set = bitrate worked out from the duration, two-pass on the cpu
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncoderProfile {
+    pub codec: VideoCodec,
+    pub quality: QualityPreset,
+    pub crf: Option<u32>,
+    pub bitrate_kbps: Option<u32>,
+    pub keyframe_interval: Option<u32>,   // frames between keyframes, None = encoder default
+    pub frame_rate: Option<f64>,          // None = keep the source rate
+    pub pixel_format: String,
+    pub audio_bitrate_kbps: u32,
+    pub audio_sample_rate: Option<u32>,
+    pub target_size_mb: Option<f64>,
+}
+
+impl Default for EncoderProfile {
+    fn default() -> Self {
+        Self {
+            codec: VideoCodec::H264,
+            quality: QualityPreset::Standard,
+            crf: None,
+            bitrate_kbps: None,
+            keyframe_interval: None,
+            frame_rate: None,
+            pixel_format: "yuv420p".to_string(),
+            audio_bitrate_kbps: 192,
+            audio_sample_rate: None,
+            target_size_mb: None,
+        }
+    }
+}
+
+impl EncoderProfile {
+    // 2d. throwaway settings for layout previews, h264 so anything can play it
+    pub fn preview() -> Self {
+        Self {
+            quality: QualityPreset::Preview,
+            audio_bitrate_kbps: 96,
+            ..Self::default()
+        }
+    }
+
+    // 3a. which backend really encodes this profile
+    // anything the gpu cant do drops to the cpu encoder for that codec
+    // fit to size always runs on the cpu, hw encoders cant do a real second pass
+    pub fn backend(&self, gpu: GpuEncoder) -> GpuEncoder {
+        if self.target_size_mb.is_some() {
+            return GpuEncoder::None;
+        }
+        let supported = match gpu {
+            GpuEncoder::Nvenc => matches!(self.codec, VideoCodec::H264 | VideoCodec::Hevc | VideoCodec::Av1),
+            GpuEncoder::Vaapi => true,
+            GpuEncoder::Videotoolbox => matches!(self.codec, VideoCodec::H264 | VideoCodec::Hevc),
+            GpuEncoder::None => true,
+        };
+        if supported { gpu } else { GpuEncoder::None }
+    }
+
+    // 3b. ffmpeg encoder name for a backend
+    pub fn encoder_name(&self, gpu: GpuEncoder) -> &'static str {
+        match (self.backend(gpu), self.codec) {
+            (GpuEncoder::Nvenc, VideoCodec::H264) => "h264_nvenc",
+            (GpuEncoder::Nvenc, VideoCodec::Hevc) => "hevc_nvenc",
+            (GpuEncoder::Nvenc, _) => "av1_nvenc",
+            (GpuEncoder::Vaapi, VideoCodec::H264) => "h264_vaapi",
+            (GpuEncoder::Vaapi, VideoCodec::Hevc) => "hevc_vaapi",
+            (GpuEncoder::Vaapi, VideoCodec::Av1) => "av1_vaapi",
+            (GpuEncoder::Vaapi, VideoCodec::Vp9) => "vp9_vaapi",
+            (GpuEncoder::Videotoolbox, VideoCodec::Hevc) => "hevc_videotoolbox",
+            (GpuEncoder::Videotoolbox, _) => "h264_videotoolbox",
+            (GpuEncoder::None, VideoCodec::H264) => "libx264",
+            (GpuEncoder::None, VideoCodec::Hevc) => "libx265",
+            (GpuEncoder::None, VideoCodec::Av1) => "libsvtav1",
+            (GpuEncoder::None, VideoCodec::Vp9) => "libvpx-vp9",
+        }
+    }
+
+    // 4a. video encoding args
+    pub fn video_args(&self, gpu: GpuEncoder) -> Vec<String> {
+        let backend = self.backend(gpu);
+        let q = self.crf.unwrap_or_else(|| self.default_quality(backend));
+        let mut args: Vec<String> = Vec::new();
+
+        // vaapi's device + upload get added by the processor, see hwaccel::upload_args
+        args.extend(["-c:v".to_string(), self.encoder_name(gpu).to_string()]);
+
+        let preset = self.preset(backend);
+        match (backend, self.codec) {
+            (GpuEncoder::None, VideoCodec::Vp9) => {
+                let (deadline, cpu_used) = preset.split_once(':').unwrap_or(("good", "4"));
+                args.extend([
+                    "-deadline".to_string(), deadline.to_string(),
+                    "-cpu-used".to_string(), cpu_used.to_string(),
+                    "-row-mt".to_string(), "1".to_string(),
+                ]);
+            }
+            _ if !preset.is_empty() => args.extend(["-preset".to_string(), preset.to_string()]),
+            _ => {}
+        }
+
+        match (self.bitrate_kbps, backend) {
+            (Some(kbps), _) => {
+                if backend == GpuEncoder::Nvenc {
+                    args.extend(["-rc".to_string(), "vbr".to_string()]);
+                }
+                args.extend([
+                    "-b:v".to_string(), format!("{}k", kbps),
+                    "-maxrate".to_string(), format!("{}k", kbps * 3 / 2),
+                    "-bufsize".to_string(), format!("{}k", kbps * 2),
+                ]);
+            }
+            (None, GpuEncoder::Nvenc) => args.extend([
+                "-rc".to_string(), "vbr".to_string(),
+                "-cq".to_string(), q.to_string(),
+            ]),
+            (None, GpuEncoder::Vaapi) => args.extend(["-qp".to_string(), q.to_string()]),
+            (None, GpuEncoder::Videotoolbox) => args.extend(["-q:v".to_string(), q.to_string()]),
+            (None, GpuEncoder::None) => {
+                args.extend(["-crf".to_string(), q.to_string()]);
+                // vp9 only does constant quality with the bitrate zeroed
+                if self.codec == VideoCodec::Vp9 {
+                    args.extend(["-b:v".to_string(), "0".to_string()]);
+                }
+            }
+        }
+
+        if let Some(frames) = self.keyframe_interval.filter(|f| *f > 0) {
+            args.extend(["-g".to_string(), frames.to_string()]);
+        }
+        if let Some(fps) = self.frame_rate.filter(|f| *f > 0.0) {
+            args.extend(["-r".to_string(), format!("{}", fps)]);
+        }
+
+        // vaapi frames live on the gpu, the format there is set by the upload
+        if backend != GpuEncoder::Vaapi && !self.pixel_format.is_empty() {
+            args.extend(["-pix_fmt".to_string(), self.pixel_format.clone()]);
+        }
+
+        // quicktime/safari only play hevc tagged hvc1
+        if self.codec == VideoCodec::Hevc {
+            args.extend(["-tag:v".to_string(), "hvc1".to_string()]);
+        }
+
+        args
+    }
+
+    // 4b. audio encoding args, every pass that re-encodes audio uses these
+    pub fn audio_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-c:a".to_string(), "aac".to_string(),
+            "-b:a".to_string(), format!("{}k", self.audio_bitrate_kbps),
+        ];
+        if let Some(rate) = self.audio_sample_rate {
+            args.extend(["-ar".to_string(), rate.to_string()]);
+        }
+        args
+    }
+
+    // 4c. fit to size - video bitrate for a clip this long
+    // mb are MiB like the file size display, ~2% is left for the mp4 container
+    pub fn fit_to_duration(&self, duration: f64) -> Self {
+        let mut profile = self.clone();
+        if let Some(mb) = self.target_size_mb.filter(|mb| *mb > 0.0 && duration > 0.0) {
+            let total_kbps = mb * 1024.0 * 1024.0 * 8.0 / 1000.0 / duration * 0.98;
+            profile.bitrate_kbps = Some((total_kbps - self.audio_bitrate_kbps as f64).max(100.0) as u32);
+        }
+        profile
+    }
+
+    // 4d. two-pass only means something for x264/x265 here
+    // av1/vp9 with a target size still get the bitrate, just in one pass
+    pub fn two_pass(&self) -> bool {
+        self.target_size_mb.is_some()
+            && self.bitrate_kbps.is_some()
+            && matches!(self.codec, VideoCodec::H264 | VideoCodec::Hevc)
+    }
+
+    // args for pass 1 or 2, log is the stats file prefix both passes share
+    pub fn pass_args(&self, pass: u8, log: &Path) -> Vec<String> {
+        let log = log.to_string_lossy().to_string();
+        match self.codec {
+            VideoCodec::Hevc => vec![
+                "-x265-params".to_string(), format!("pass={}:stats={}.log", pass, log),
+            ],
+            _ => vec![
+                "-pass".to_string(), pass.to_string(),
+                "-passlogfile".to_string(), log,
+            ],
+        }
+    }
+
+    // draft / standard / high per encoder
+    // vp9 comes back as "deadline:cpu-used", videotoolbox has no presets
+    fn preset(&self, backend: GpuEncoder) -> &'static str {
+        let pick = |presets: [&'static str; 4]| match self.quality {
+            QualityPreset::Draft => presets[0],
+            QualityPreset::Standard => presets[1],
+            QualityPreset::High => presets[2],
+            QualityPreset::Preview => presets[3],
+        };
+        match (backend, self.codec) {
+            (GpuEncoder::Nvenc, _) => pick(["p2", "p4", "p6", "p1"]),
+            (GpuEncoder::Vaapi, _) | (GpuEncoder::Videotoolbox, _) => "",
+            (GpuEncoder::None, VideoCodec::H264) | (GpuEncoder::None, VideoCodec::Hevc) => pick(["veryfast", "fast", "slow", "ultrafast"]),
+            (GpuEncoder::None, VideoCodec::Av1) => pick(["10", "8", "5", "12"]),
+            (GpuEncoder::None, VideoCodec::Vp9) => pick(["realtime:8", "good:4", "good:1", "realtime:8"]),
+        }
+    }
+
+    // default crf/cq/qp - standard h264 is the old hard coded 23
+    fn default_quality(&self, backend: GpuEncoder) -> u32 {
+        let pick = |values: [u32; 4]| match self.quality {
+            QualityPreset::Draft => values[0],
+            QualityPreset::Standard => values[1],
+            QualityPreset::High => values[2],
+            QualityPreset::Preview => values[3],
+        };
+        match (backend, self.codec) {
+            (GpuEncoder::Videotoolbox, _) => pick([50, 65, 80, 40]),  // higher = better here
+            (GpuEncoder::Nvenc, _) | (GpuEncoder::Vaapi, _) => pick([28, 23, 19, 32]),
+            (GpuEncoder::None, VideoCodec::H264) => pick([28, 23, 18, 30]),
+            (GpuEncoder::None, VideoCodec::Hevc) => pick([30, 26, 22, 32]),
+            (GpuEncoder::None, VideoCodec::Av1) => pick([38, 32, 26, 42]),
+            (GpuEncoder::None, VideoCodec::Vp9) => pick([40, 33, 28, 45]),
+        }
+    }
+}
+
+// 5a. tests - all on the cpu path so they run anywhere
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(profile: &EncoderProfile) -> String {
+        profile.video_args(GpuEncoder::None).join(" ")
+    }
+
+    #[test]
+    fn test_default_is_the_old_x264_setup() {
+        assert_eq!(
+            args(&EncoderProfile::default()),
+            "-c:v libx264 -preset fast -crf 23 -pix_fmt yuv420p"
+        );
+        assert_eq!(EncoderProfile::default().audio_args().join(" "), "-c:a aac -b:a 192k");
+        assert_eq!(args(&EncoderProfile::preview()), "-c:v libx264 -preset ultrafast -crf 30 -pix_fmt yuv420p");
+    }
+
+    #[test]
+    fn test_every_codec_and_preset_on_cpu() {
+        for codec in [VideoCodec::H264, VideoCodec::Hevc, VideoCodec::Av1, VideoCodec::Vp9] {
+            for quality in [QualityPreset::Draft, QualityPreset::Standard, QualityPreset::High] {
+                let profile = EncoderProfile { codec, quality, ..EncoderProfile::default() };
+                let line = args(&profile);
+                assert!(line.contains(profile.encoder_name(GpuEncoder::None)), "{}", line);
+                assert!(line.contains("-crf "), "{}", line);
+            }
+        }
+
+        let av1 = EncoderProfile { codec: VideoCodec::Av1, quality: QualityPreset::High, ..EncoderProfile::default() };
+        assert_eq!(args(&av1), "-c:v libsvtav1 -preset 5 -crf 26 -pix_fmt yuv420p");
+
+        let vp9 = EncoderProfile { codec: VideoCodec::Vp9, quality: QualityPreset::Draft, ..EncoderProfile::default() };
+        assert_eq!(args(&vp9), "-c:v libvpx-vp9 -deadline realtime -cpu-used 8 -row-mt 1 -crf 40 -b:v 0 -pix_fmt yuv420p");
+    }
+
+    #[test]
+    fn test_bitrate_keyframes_and_gpu_fallback() {
+        let profile = EncoderProfile {
+            codec: VideoCodec::Hevc,
+            bitrate_kbps: Some(6000),
+            keyframe_interval: Some(60),
+            pixel_format: "yuv420p10le".to_string(),
+            audio_bitrate_kbps: 320,
+            ..EncoderProfile::default()
+        };
+        assert_eq!(
+            args(&profile),
+            "-c:v libx265 -preset fast -b:v 6000k -maxrate 9000k -bufsize 12000k -g 60 -pix_fmt yuv420p10le -tag:v hvc1"
+        );
+        assert_eq!(profile.audio_args()[3], "320k");
+
+        // videotoolbox cant do vp9, so the cpu does it
+        let vp9 = EncoderProfile { codec: VideoCodec::Vp9, ..EncoderProfile::default() };
+        assert_eq!(vp9.encoder_name(GpuEncoder::Videotoolbox), "libvpx-vp9");
+        assert_eq!(EncoderProfile::default().encoder_name(GpuEncoder::Nvenc), "h264_nvenc");
+    }
+
+    #[test]
+    fn test_fit_to_size_is_two_pass_x264() {
+        let profile = EncoderProfile { target_size_mb: Some(50.0), ..EncoderProfile::default() };
+        assert!(!profile.two_pass());  // no bitrate until it knows the duration
+        assert_eq!(profile.encoder_name(GpuEncoder::Nvenc), "libx264");
+
+        // 50 MiB over 100s = ~4111kbps total, minus 192k audio
+        let fitted = profile.fit_to_duration(100.0);
+        assert_eq!(fitted.bitrate_kbps, Some(3918));
+        assert!(fitted.two_pass());
+        assert_eq!(fitted.pass_args(1, Path::new("/tmp/x")).join(" "), "-pass 1 -passlogfile /tmp/x");
+
+        let hevc = EncoderProfile { codec: VideoCodec::Hevc, ..fitted.clone() };
+        assert_eq!(hevc.pass_args(2, Path::new("/tmp/x")).join(" "), "-x265-params pass=2:stats=/tmp/x.log");
+
+        // tiny targets still leave something to encode with
+        assert_eq!(profile.fit_to_duration(100_000.0).bitrate_kbps, Some(100));
+    }
+}
@@ -0,0 +1,132 @@
+// 1a. filters.rs - helpers for building ffmpeg filter strings
+// 1b. the composite functions were getting stuffed with format! soup
+// 1c. anything thats pure string building for filtergraphs lives here
+
+// 2a. piecewise linear expression over time
+// points are (seconds, value), sorted by time
+// holds the first value before the first point and the last value after the last
+// built as a flat sum of gated segments instead of nested if() cuz
+// ffmpeg's expression parser gets unhappy with deep nesting
+pub fn piecewise_linear_expr(points: &[(f64, f64)]) -> String {
+    match points {
+        [] => "0".to_string(),
+        [(_, value)] => format!("{:.3}", value),
+        _ => {
+            let (first_t, first_v) = points[0];
+            let (last_t, last_v) = points[points.len() - 1];
+
+            let mut terms = vec![format!("lt(t,{:.3})*{:.3}", first_t, first_v)];
+
+            for pair in points.windows(2) {
+                let (t0, v0) = pair[0];
+                let (t1, v1) = pair[1];
+                let span = t1 - t0;
+                if span <= 0.0 {
+                    continue;
+                }
+                let slope = (v1 - v0) / span;
+                terms.push(format!(
+                    "gte(t,{:.3})*lt(t,{:.3})*({:.3}+(t-{:.3})*{:.5})",
+                    t0, t1, v0, t0, slope
+                ));
+            }
+
+            terms.push(format!("gte(t,{:.3})*{:.3}", last_t, last_v));
+            terms.join("+")
+        }
+    }
+}
+
+// 2b. quote a file path for use as a filter option value
+// the graph parser eats the quotes, then the option parser wants ':' escaped
+// backslashes become forward slashes which ffmpeg is fine with on windows too
+pub fn escape_filter_path(path: &std::path::Path) -> String {
+    let escaped = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .replace(':', "\\:")
+        .replace('\'', "'\\''");
+    format!("'{}'", escaped)
+}
+
+// 2c. escape a free-text option value (drawtext text, font names)
+// two rounds: once for the option parser, once more for the graph parser
+// so colons, quotes, commas and brackets in user text dont break the graph
+pub fn escape_filter_value(value: &str) -> String {
+    let escape = |input: &str, special: &[char]| -> String {
+        let mut out = String::with_capacity(input.len());
+        for c in input.chars() {
+            if special.contains(&c) {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    };
+
+    let option_level = escape(value, &['\\', '\'', ':']);
+    escape(&option_level, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+// 2d. is this safe to drop into a filter option as a color
+// #rrggbb, 0xrrggbb or a plain name like "white" - anything else could carry : , ; [ ' into the graph
+// #rrggbbaa only when allow_alpha, callers with their own opacity knob append @alpha themselves
+pub fn is_filter_color(value: &str, allow_alpha: bool) -> bool {
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .or_else(|| value.strip_prefix("0X"));
+
+    match hex {
+        Some(digits) => {
+            (digits.len() == 6 || (allow_alpha && digits.len() == 8))
+                && digits.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => !value.is_empty() && value.len() <= 32 && value.chars().all(|c| c.is_ascii_alphabetic()),
+    }
+}
+
+// 3a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piecewise_constant() {
+        assert_eq!(piecewise_linear_expr(&[]), "0");
+        assert_eq!(piecewise_linear_expr(&[(2.0, 40.0)]), "40.000");
+    }
+
+    #[test]
+    fn test_escape_filter_path() {
+        let path = std::path::Path::new("C:\\videos\\subs.ass");
+        assert_eq!(escape_filter_path(path), "'C\\:/videos/subs.ass'");
+    }
+
+    #[test]
+    fn test_escape_filter_value() {
+        assert_eq!(escape_filter_value("plain text"), "plain text");
+        assert_eq!(escape_filter_value("time: 5, go"), "time\\\\: 5\\, go");
+        assert_eq!(escape_filter_value("it's"), "it\\\\\\'s");
+    }
+
+    #[test]
+    fn test_filter_colors() {
+        for ok in ["white", "#ff00aa", "0xFF00AA", "#000000"] {
+            assert!(is_filter_color(ok, false), "{}", ok);
+        }
+        assert!(is_filter_color("#ff00aa80", true));
+        for bad in ["#ff00aa80", "black@0.5", "red:x=0", "red,null", "#ff00a", "", "white'"] {
+            assert!(!is_filter_color(bad, false), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_piecewise_segments() {
+        let expr = piecewise_linear_expr(&[(0.0, 0.0), (2.0, 100.0)]);
+        assert_eq!(
+            expr,
+            "lt(t,0.000)*0.000+gte(t,0.000)*lt(t,2.000)*(0.000+(t-0.000)*50.00000)+gte(t,2.000)*100.000"
+        );
+    }
+}
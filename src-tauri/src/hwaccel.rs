@@ -0,0 +1,146 @@
+// 1a. hwaccel.rs - hardware encoder detection
+// 1b. an encoder showing up in `ffmpeg -encoders` only means it was compiled in,
+// the driver/gpu can still be missing. so each one gets a tiny real encode first
+// 1c. runs once as part of the capabilities probe, probing takes a second or two
+
+use std::path::{Path, PathBuf};
+use crate::processor::GpuEncoder;
+
+// 2a. what the probe found
+// vaapi needs to know which render node actually worked
+#[derive(Debug, Clone)]
+pub struct HwEncoder {
+    pub kind: GpuEncoder,
+    pub vaapi_device: Option<PathBuf>,
+}
+
+// 3a. the usable hw encoder out of the ones ffmpeg was built with
+// checks in order of preference: nvidia > vaapi > videotoolbox > cpu
+pub fn probe(compiled_in: &[String]) -> HwEncoder {
+    let has = |name: &str| compiled_in.iter().any(|e| e == name);
+
+    // nvenc is usually the fastest option
+    if has("h264_nvenc") && test_encode("h264_nvenc", None) {
+        return HwEncoder { kind: GpuEncoder::Nvenc, vaapi_device: None };
+    }
+
+    // vaapi (amd/intel on linux) - renderD128 isnt always the gpu that works,
+    // laptops with two gpus have 128 and 129
+    if has("h264_vaapi") {
+        for device in render_nodes() {
+            if test_encode("h264_vaapi", Some(&device)) {
+                return HwEncoder { kind: GpuEncoder::Vaapi, vaapi_device: Some(device) };
+            }
+        }
+    }
+
+    // videotoolbox (macos)
+    if has("h264_videotoolbox") && test_encode("h264_videotoolbox", None) {
+        return HwEncoder { kind: GpuEncoder::Videotoolbox, vaapi_device: None };
+    }
+
+    // no gpu encoder that works, use cpu
+    HwEncoder { kind: GpuEncoder::None, vaapi_device: None }
+}
+
+// 3b. a few frames of black through the encoder, thrown away
+// same arg rewrite as the real renders so a broken upload shows up here too
+fn test_encode(encoder: &str, vaapi_device: Option<&Path>) -> bool {
+    let mut args: Vec<String> = [
+        "-hide_banner", "-loglevel", "error",
+        "-f", "lavfi", "-i", "color=c=black:s=256x256:r=30:d=0.2",
+        "-vf", "format=yuv420p",
+        "-frames:v", "5",
+        "-c:v", encoder,
+        "-f", "null", "-",
+    ].iter().map(|s| s.to_string()).collect();
+    if let Some(device) = vaapi_device {
+        args = upload_args(args, device);
+    }
+
+    let works = std::process::Command::new("ffmpeg")
+        .args(&args)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    log::info!("probe {} ({}): {}", encoder, vaapi_device.map(|d| d.display().to_string()).unwrap_or_default(), if works { "ok" } else { "unusable" });
+    works
+}
+
+// 3c. /dev/dri render nodes, lowest first
+fn render_nodes() -> Vec<PathBuf> {
+    let mut nodes: Vec<PathBuf> = std::fs::read_dir("/dev/dri")
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("renderD")))
+                .collect()
+        })
+        .unwrap_or_default();
+    nodes.sort();
+    nodes
+}
+
+// 4a. make an encode's args work with vaapi
+// vaapi encoders only take frames already on the gpu, so the end of the video
+// filter chain gets format=nv12,hwupload. video is always the first -map from a graph
+pub fn upload_args(mut args: Vec<String>, device: &Path) -> Vec<String> {
+    const UPLOAD: &str = "format=nv12,hwupload";
+
+    let filter_at = args.iter().position(|a| a == "-filter_complex").map(|i| i + 1);
+    let map_at = args
+        .windows(2)
+        .position(|w| w[0] == "-map" && w[1].starts_with('['))
+        .map(|i| i + 1);
+    let vf_at = args.iter().position(|a| a == "-vf").map(|i| i + 1);
+
+    match (filter_at, map_at, vf_at) {
+        (Some(filter), Some(map), _) if filter < args.len() => {
+            let label = args[map].clone();
+            args[filter] = format!("{};{}{}[hwout]", args[filter], label, UPLOAD);
+            args[map] = "[hwout]".to_string();
+        }
+        (_, _, Some(vf)) if vf < args.len() => {
+            args[vf] = format!("{},{}", args[vf], UPLOAD);
+        }
+        _ => {
+            // no filter yet, output args go before the output path at the end
+            let at = args.len().saturating_sub(1);
+            args.splice(at..at, ["-vf".to_string(), UPLOAD.to_string()]);
+        }
+    }
+
+    args.splice(0..0, ["-vaapi_device".to_string(), device.to_string_lossy().to_string()]);
+    args
+}
+
+// 5a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_upload_goes_on_the_end_of_the_chain() {
+        let device = Path::new("/dev/dri/renderD129");
+
+        let graph = upload_args(strings(&[
+            "-y", "-i", "a.mp4", "-filter_complex", "[0:v]scale=1080:1920[out]",
+            "-map", "[out]", "-map", "0:a?", "-c:v", "h264_vaapi", "out.mp4",
+        ]), device);
+        assert_eq!(&graph[..2], ["-vaapi_device", "/dev/dri/renderD129"]);
+        assert!(graph.contains(&"[0:v]scale=1080:1920[out];[out]format=nv12,hwupload[hwout]".to_string()));
+        assert!(graph.windows(2).any(|w| w[0] == "-map" && w[1] == "[hwout]"));
+        assert!(graph.windows(2).any(|w| w[0] == "-map" && w[1] == "0:a?"));
+
+        let vf = upload_args(strings(&["-i", "a.mp4", "-vf", "fps=30", "-c:v", "h264_vaapi", "out.mp4"]), device);
+        assert!(vf.contains(&"fps=30,format=nv12,hwupload".to_string()));
+
+        let bare = upload_args(strings(&["-i", "a.mp4", "-c:v", "h264_vaapi", "out.mp4"]), device);
+        assert_eq!(bare[bare.len() - 3..], ["-vf", "format=nv12,hwupload", "out.mp4"]);
+    }
+}
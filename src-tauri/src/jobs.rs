@@ -4,7 +4,7 @@
 // tbh this took forever to get right with the async stuff
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
@@ -12,17 +12,39 @@ use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::downloader::{Downloader, VideoInfo};
-use crate::processor::{Processor, Dimensions};
+use crate::processor::{Processor, Dimensions, Layout};
+use crate::encoder::EncoderProfile;
+use crate::proxy::{ProxyCache, ProxySettings};
+use crate::presets::ExportPreset;
 use crate::scrambler::{Scrambler, ScrambleConfig};
+use crate::reframe::{self, CropPath, ReframeConfig};
+use crate::pip::PipStyle;
+use crate::captions::{self, CaptionOptions, CaptionPlacement, CaptionStyle};
+use crate::subtitles;
+use crate::layers::{CompositeLayers, TextOverlay, Watermark};
+use crate::bumpers::Bumpers;
+use crate::scrambler::CutClip;
+use crate::sfx::{SfxLibrary, SfxPlacement};
+use crate::sfx_categories::SfxCategories;
+use crate::sfx_cues;
+use crate::sfx_placement::{self, AutoPlacement};
+use crate::audio::{self, LoudnessReport, MasteringOptions, MusicBed};
+use crate::utils;
+use crate::capabilities::Capabilities;
 
 // 2a. output format presets for different platforms
 // each platform has their own aspect ratio preferences
+// the full rules (length, size, bitrate...) live in presets.rs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OutputFormat {
-    YouTube,    // 1920x1080 - landscape boi
-    TikTok,     // 1080x1920 - vertical gang
-    Instagram,  // 1080x1350 - 4:5 ratio
-    Custom,     // user picks their own dimensions
+    YouTube,         // 1920x1080 - landscape boi
+    YouTubeShorts,   // 1080x1920 - 3 min max
+    TikTok,          // 1080x1920 - vertical gang
+    InstagramReels,  // 1080x1920 - 3 min max
+    #[serde(alias = "Instagram")]
+    InstagramFeed,   // 1080x1350 - 4:5 ratio
+    X,               // 1920x1080 - 2:20 max
+    Custom,          // user picks their own dimensions
 }
 
 // 2b. where the broll footage comes from
@@ -52,23 +74,109 @@ impl Default for OverlayPosition {
     }
 }
 
+impl OverlayPosition {
+    // what the frontend sends, anything unknown is the classic top split
+    pub fn from_value(value: &str) -> Self {
+        match value {
+            "bottom" => OverlayPosition::Bottom,
+            "topleft" | "top-left" => OverlayPosition::TopLeft,
+            "topright" | "top-right" => OverlayPosition::TopRight,
+            "bottomleft" | "bottom-left" => OverlayPosition::BottomLeft,
+            "bottomright" | "bottom-right" => OverlayPosition::BottomRight,
+            "sidebyside" | "side-by-side" => OverlayPosition::SideBySide,
+            _ => OverlayPosition::Top,
+        }
+    }
+
+    // the layout for this position and which video goes in as input 0 / 1
+    // split_ratio is always the broll's share, the user crop follows the user video around
+    pub fn layout<'a>(
+        &self,
+        split_ratio: f64,
+        pip_style: &'a PipStyle,
+        user_crop: Option<&'a CropPath>,
+        broll: &'a Path,
+        user: &'a Path,
+    ) -> (Layout<'a>, [&'a Path; 2]) {
+        match self {
+            OverlayPosition::Top => (
+                Layout::SplitScreen { ratio: split_ratio, crops: [None, user_crop] },
+                [broll, user],
+            ),
+            OverlayPosition::Bottom => (
+                Layout::SplitScreen { ratio: 1.0 - split_ratio, crops: [user_crop, None] },  // swap order - user on top
+                [user, broll],
+            ),
+            // picture in picture - user is the main video, broll is the overlay
+            OverlayPosition::TopLeft | OverlayPosition::TopRight |
+            OverlayPosition::BottomLeft | OverlayPosition::BottomRight => (
+                Layout::Pip(pip_style),
+                [user, broll],
+            ),
+            OverlayPosition::SideBySide => (
+                Layout::SideBySide { ratio: split_ratio, crops: [None, user_crop] },
+                [broll, user],
+            ),
+        }
+    }
+}
+
 impl OutputFormat {
+    pub fn all() -> &'static [OutputFormat] {
+        &[
+            OutputFormat::YouTube,
+            OutputFormat::YouTubeShorts,
+            OutputFormat::TikTok,
+            OutputFormat::InstagramReels,
+            OutputFormat::InstagramFeed,
+            OutputFormat::X,
+            OutputFormat::Custom,
+        ]
+    }
+
+    // parse the dropdown value, unknown stuff falls back to youtube
+    pub fn from_value(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "youtube-shorts" | "shorts" => OutputFormat::YouTubeShorts,
+            "tiktok" => OutputFormat::TikTok,
+            "instagram-reels" | "reels" => OutputFormat::InstagramReels,
+            "instagram" | "instagram-feed" => OutputFormat::InstagramFeed,
+            "x" | "twitter" => OutputFormat::X,
+            "custom" => OutputFormat::Custom,
+            _ => OutputFormat::YouTube,
+        }
+    }
+
+    pub fn preset(&self) -> ExportPreset {
+        ExportPreset::for_format(self)
+    }
+
     pub fn dimensions(&self) -> Dimensions {
         match self {
-            OutputFormat::YouTube => Dimensions::youtube(),
-            OutputFormat::TikTok => Dimensions::tiktok(),
-            OutputFormat::Instagram => Dimensions::instagram(),
+            OutputFormat::YouTube | OutputFormat::X => Dimensions::youtube(),
+            OutputFormat::TikTok | OutputFormat::YouTubeShorts | OutputFormat::InstagramReels => Dimensions::tiktok(),
+            OutputFormat::InstagramFeed => Dimensions::instagram(),
             OutputFormat::Custom => Dimensions::youtube(), // fallback, actual dims come from config
         }
     }
     
     pub fn name(&self) -> &str {
-        match self {
-            OutputFormat::YouTube => "YouTube",
-            OutputFormat::TikTok => "TikTok",
-            OutputFormat::Instagram => "Instagram",
-            OutputFormat::Custom => "Custom",
-        }
+        self.preset().label
+    }
+}
+
+// 2d. preview mode - a short low res render to check the layout
+// same composite path as the real thing, minus everything slow (transcribing, mastering, bumpers)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewOptions {
+    pub seconds: f64,  // from the start of the user video
+    pub height: u32,   // short side, 480 = 854x480 landscape / 480x854 portrait
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self { seconds: 10.0, height: 480 }
     }
 }
 
@@ -77,7 +185,7 @@ impl OutputFormat {
 pub struct JobConfig {
     pub broll_source: BrollSource,     // where broll comes from
     pub user_video_path: String,        // the talking head video
-    pub output_format: OutputFormat,    // preset or custom
+    pub output_formats: Vec<OutputFormat>,  // one render per format, broll is shared
     pub sfx_folder: Option<String>,     // optional sound effects
     pub max_clip_duration: f64,         // longest a clip can be (default 4s)
     pub min_clip_duration: f64,         // shortest a clip can be (default 1.5s)
@@ -86,6 +194,122 @@ pub struct JobConfig {
     pub custom_height: Option<u32>,     // for custom output size
     pub split_ratio: f64,               // how much screen broll takes (0.3-0.7)
     pub pip_scale: f64,                 // for pip modes, how big the overlay is
+    pub auto_reframe: bool,             // track the speaker in split/side layouts
+    pub pip_style: Option<PipStyle>,    // full pip styling, overrides pip_scale + corner
+    pub captions: Option<CaptionOptions>,  // transcribe + caption the user video
+    pub subtitles_path: Option<String>,    // existing srt/vtt to burn in instead
+    pub subtitle_style: CaptionStyle,      // look of the imported subs
+    pub title: Option<String>,             // hook title, drawn as a bar on the split line
+    pub text_overlays: Vec<TextOverlay>,   // any other text (ctas, labels...)
+    pub watermark: Option<Watermark>,      // logo png in a corner
+    pub bumpers: Option<Bumpers>,          // branded intro/outro clips
+    pub mastering: MasteringOptions,       // loudness/cleanup on the final mix
+    pub music: Option<MusicBed>,           // background track ducked under the voice
+    pub sfx_placement: AutoPlacement,      // how busy the auto placed sfx get
+    pub sfx_cues: Option<String>,          // csv/json cue sheet, replaces auto placement
+    pub encoder: Option<EncoderProfile>,   // codec + quality, None = each platform's own
+    pub preview: Option<PreviewOptions>,   // Some = quick layout check, not a real export
+    pub use_proxies: bool,                 // cut broll from cached mezzanine copies
+    pub frame_accurate: bool,              // frame exact cuts, broll length matches the video
+}
+
+impl JobConfig {
+    // the profile a format renders with
+    pub fn encoder_for(&self, format: &OutputFormat) -> EncoderProfile {
+        self.encoder.clone().unwrap_or_else(|| format.preset().encoder_profile())
+    }
+
+    // pip box for the corner modes, stretched over a video this long
+    // no explicit style = the classic corner box at pip_scale
+    pub fn pip_style_for(&self, duration: f64) -> PipStyle {
+        let mut style = self.pip_style.clone().unwrap_or_else(|| {
+            PipStyle::for_corner(self.overlay_position, self.pip_scale)
+        });
+        style.fit_to_duration(duration);
+        style
+    }
+
+    // custom size only applies to the custom format
+    // previews shrink it so the short side is preview.height, same aspect
+    pub fn dimensions_for(&self, format: &OutputFormat) -> Dimensions {
+        let full = if let OutputFormat::Custom = format {
+            Dimensions {
+                width: self.custom_width.unwrap_or(1920),
+                height: self.custom_height.unwrap_or(1080),
+            }
+        } else {
+            format.dimensions()
+        };
+        match &self.preview {
+            Some(preview) if full.width.min(full.height) > preview.height => {
+                let scale = preview.height as f64 / full.width.min(full.height) as f64;
+                let even = |side: u32| ((side as f64 * scale / 2.0).round() as u32 * 2).max(2);
+                Dimensions { width: even(full.width), height: even(full.height) }
+            }
+            _ => full,
+        }
+    }
+
+    // the part of a format's frame the broll actually fills
+    // split/side get their share of the frame, pip gets its box
+    pub fn broll_region(&self, format: &OutputFormat) -> Dimensions {
+        let full = self.dimensions_for(format);
+        let scaled = |side: u32, share: f64| ((side as f64 * share).round() as u32).max(2);
+        match self.overlay_position {
+            OverlayPosition::Top | OverlayPosition::Bottom => Dimensions {
+                width: full.width,
+                height: scaled(full.height, self.split_ratio),
+            },
+            OverlayPosition::SideBySide => Dimensions {
+                width: scaled(full.width, self.split_ratio),
+                height: full.height,
+            },
+            OverlayPosition::TopLeft | OverlayPosition::TopRight |
+            OverlayPosition::BottomLeft | OverlayPosition::BottomRight => {
+                let size = self.pip_style.as_ref().map(|s| s.size).unwrap_or(self.pip_scale);
+                Dimensions { width: scaled(full.width, size), height: scaled(full.height, size) }
+            }
+        }
+    }
+
+    // size + fps broll proxies get made at - big enough to cover the broll region
+    // of every format, at the rate the renders come out at. make_proxy never goes past the source size
+    pub fn proxy_settings(&self, source_fps: f64) -> ProxySettings {
+        let regions: Vec<Dimensions> = self.output_formats.iter().map(|f| self.broll_region(f)).collect();
+        let fps = self.output_formats
+            .first()
+            .and_then(|f| self.encoder_for(f).frame_rate)
+            .unwrap_or(source_fps);
+        ProxySettings {
+            size: Dimensions {
+                width: regions.iter().map(|d| d.width).max().unwrap_or(1920),
+                height: regions.iter().map(|d| d.height).max().unwrap_or(1080),
+            },
+            fps: if fps > 0.0 { fps } else { 30.0 },
+        }
+    }
+
+    // the same job as a preview - first format only, and the slow optional
+    // passes left out since they dont change the layout
+    pub fn into_preview(self, options: PreviewOptions) -> Self {
+        let broll_source = match self.broll_source {
+            // one download is plenty for a few seconds of clips
+            BrollSource::YouTube(links) => BrollSource::YouTube(links.into_iter().take(1).collect()),
+            local => local,
+        };
+        Self {
+            broll_source,
+            output_formats: self.output_formats.into_iter().take(1).collect(),
+            sfx_folder: None,
+            captions: None,
+            bumpers: None,
+            mastering: MasteringOptions { enabled: false, ..self.mastering },
+            sfx_cues: None,
+            encoder: Some(EncoderProfile::preview()),
+            preview: Some(options),
+            ..self
+        }
+    }
 }
 
 impl Default for JobConfig {
@@ -93,7 +317,7 @@ impl Default for JobConfig {
         Self {
             broll_source: BrollSource::LocalFiles(vec![]),
             user_video_path: String::new(),
-            output_format: OutputFormat::YouTube,
+            output_formats: vec![OutputFormat::YouTube],
             sfx_folder: None,
             max_clip_duration: 4.0,
             min_clip_duration: 1.5,
@@ -102,6 +326,23 @@ impl Default for JobConfig {
             custom_height: None,
             split_ratio: 0.5,
             pip_scale: 0.3, // 30% of screen for pip
+            auto_reframe: false,
+            pip_style: None,
+            captions: None,
+            subtitles_path: None,
+            subtitle_style: CaptionStyle::default(),
+            title: None,
+            text_overlays: Vec::new(),
+            watermark: None,
+            bumpers: None,
+            mastering: MasteringOptions::default(),
+            music: None,
+            sfx_placement: AutoPlacement::default(),
+            sfx_cues: None,
+            encoder: None,
+            preview: None,
+            use_proxies: false,
+            frame_accurate: false,
         }
     }
 }
@@ -140,12 +381,26 @@ pub struct JobStatus {
     pub created_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
-    pub output_path: Option<String>,
+    pub outputs: Vec<JobOutput>,           // one per format, filled in as each finishes
+    pub sfx_events: Vec<SfxPlacement>,     // what got placed where
+    pub warnings: Vec<String>,             // platform rules the output breaks, job still runs
+    pub encoder_used: Option<String>,      // ffmpeg encoder(s) the outputs went through
     pub error: Option<String>,
-    pub output_format: String,
+    pub output_formats: Vec<String>,
     pub overlay_position: String,
 }
 
+// 3e. one finished video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobOutput {
+    pub format: String,
+    pub path: String,
+    pub sidecar_files: Vec<String>,        // srt/vtt written next to it
+    pub loudness: Option<LoudnessReport>,  // measured before/after mastering
+    pub size_bytes: u64,
+    pub encoder: String,                   // libx264, h264_nvenc... after any cpu fallback
+}
+
 // 4a. internal job struct - not serialized to frontend
 // has more detailed info needed for processing
 struct Job {
@@ -156,7 +411,9 @@ struct Job {
     created_at: DateTime<Utc>,
     started_at: Option<DateTime<Utc>>,
     completed_at: Option<DateTime<Utc>>,
-    output_path: Option<PathBuf>,
+    outputs: Vec<JobOutput>,
+    sfx_events: Vec<SfxPlacement>,
+    warnings: Vec<String>,
     error: Option<String>,
     cancelled: bool,
 }
@@ -177,7 +434,9 @@ impl Job {
             created_at: Utc::now(),
             started_at: None,
             completed_at: None,
-            output_path: None,
+            outputs: Vec::new(),
+            sfx_events: Vec::new(),
+            warnings: Vec::new(),
             error: None,
             cancelled: false,
         }
@@ -202,14 +461,28 @@ impl Job {
             created_at: self.created_at,
             started_at: self.started_at,
             completed_at: self.completed_at,
-            output_path: self.output_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+            outputs: self.outputs.clone(),
+            sfx_events: self.sfx_events.clone(),
+            warnings: self.warnings.clone(),
+            encoder_used: encoder_used(&self.outputs),
             error: self.error.clone(),
-            output_format: self.config.output_format.name().to_string(),
+            output_formats: self.config.output_formats.iter().map(|f| f.name().to_string()).collect(),
             overlay_position: overlay_name.to_string(),
         }
     }
 }
 
+// 4b. distinct encoders across a job's outputs, in render order
+fn encoder_used(outputs: &[JobOutput]) -> Option<String> {
+    let mut names: Vec<&str> = Vec::new();
+    for output in outputs {
+        if !names.contains(&output.encoder.as_str()) {
+            names.push(&output.encoder);
+        }
+    }
+    if names.is_empty() { None } else { Some(names.join(", ")) }
+}
+
 // 5a. job manager - stores and manages all jobs
 // the brain of the operation basically
 pub struct JobManager {
@@ -262,6 +535,23 @@ impl JobManager {
         }
     }
 
+    // 5f. drop a job, handing back its last status
+    // running jobs see this as a cancel
+    pub fn remove_job(&mut self, id: &str) -> Option<JobStatus> {
+        self.jobs.remove(id).map(|j| j.to_status())
+    }
+
+    // 5g. mark a job failed if run_job bailed out without saying so
+    // finished + cancelled jobs keep their state, everything else would sit in Processing forever
+    pub fn fail_unfinished(&mut self, id: &str, error: String) {
+        let unfinished = self.jobs.get(id).is_some_and(|job| {
+            !matches!(job.state, JobState::Complete | JobState::Failed | JobState::Cancelled)
+        });
+        if unfinished {
+            self.set_failed(id, error);
+        }
+    }
+
     // internal update methods - not pub cuz only used by run_job
     fn update_state(&mut self, id: &str, state: JobState) {
         if let Some(job) = self.jobs.get_mut(id) {
@@ -278,10 +568,9 @@ impl JobManager {
         }
     }
 
-    fn set_complete(&mut self, id: &str, output_path: PathBuf) {
+    fn set_complete(&mut self, id: &str) {
         if let Some(job) = self.jobs.get_mut(id) {
             job.state = JobState::Complete;
-            job.output_path = Some(output_path);
             job.completed_at = Some(Utc::now());
             job.progress = JobProgress {
                 stage: "All done! Your video is ready".to_string(),
@@ -293,6 +582,31 @@ impl JobManager {
         }
     }
 
+    fn add_output(&mut self, id: &str, output: JobOutput) {
+        if let Some(job) = self.jobs.get_mut(id) {
+            job.outputs.push(output);
+        }
+    }
+
+    fn add_sidecar_files(&mut self, id: &str, video: &std::path::Path, files: Vec<PathBuf>) {
+        let video = video.to_string_lossy();
+        if let Some(output) = self.jobs.get_mut(id).and_then(|j| j.outputs.iter_mut().find(|o| o.path == video)) {
+            output.sidecar_files.extend(files.iter().map(|p| p.to_string_lossy().to_string()));
+        }
+    }
+
+    fn set_sfx_events(&mut self, id: &str, events: Vec<SfxPlacement>) {
+        if let Some(job) = self.jobs.get_mut(id) {
+            job.sfx_events = events;
+        }
+    }
+
+    fn add_warnings(&mut self, id: &str, warnings: Vec<String>) {
+        if let Some(job) = self.jobs.get_mut(id) {
+            job.warnings.extend(warnings);
+        }
+    }
+
     fn set_failed(&mut self, id: &str, error: String) {
         if let Some(job) = self.jobs.get_mut(id) {
             job.state = JobState::Failed;
@@ -302,8 +616,9 @@ impl JobManager {
         }
     }
 
+    // a removed job counts as cancelled so its run_job stops too
     fn is_cancelled(&self, id: &str) -> bool {
-        self.jobs.get(id).map(|j| j.cancelled).unwrap_or(false)
+        self.jobs.get(id).map(|j| j.cancelled).unwrap_or(true)
     }
 
     fn get_config(&self, id: &str) -> Option<JobConfig> {
@@ -314,7 +629,8 @@ impl JobManager {
 // 6a. run_job - the main processing pipeline
 // this is where all the actual work happens
 // spawned in a tokio task so it runs in background
-pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<(), String> {
+// capabilities is the app's ffmpeg probe, the processors take their gpu encoder from it
+pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String, capabilities: Arc<Capabilities>) -> Result<(), String> {
     log::info!("starting job: {}", job_id);
     
     // grab the config
@@ -340,6 +656,116 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
         mgr.is_cancelled(&job_id)
     };
 
+    // ============================================
+    // PREFLIGHT: CHECK AGAINST THE PLATFORM
+    // probe the user video first so a bad size or codec fails
+    // before we spend ages downloading broll
+    // ============================================
+
+    // get the user's video duration so we know how much broll to make
+    let processor = Processor::new(&clips_dir, &capabilities).map_err(|e| e.to_string())?;
+    let mut user_video_path = PathBuf::from(&config.user_video_path);
+    let mut user_metadata = match processor.get_metadata(&user_video_path).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            let mut mgr = manager.lock();
+            mgr.set_failed(&job_id, format!("couldnt read your video: {}", e));
+            return Err(e.to_string());
+        }
+    };
+
+    log::info!("user video duration: {:.1}s", user_metadata.duration);
+
+    // previews only need the first few seconds, everything below then works off the short copy
+    if let Some(preview) = config.preview.as_ref().filter(|p| p.seconds < user_metadata.duration) {
+        let trimmed = temp_base.join("preview_source.mp4");
+        processor.clone()
+            .with_encoder(EncoderProfile::preview())
+            .cut_clip(&user_video_path, &trimmed, 0.0, preview.seconds, false)
+            .await
+            .map_err(|e| e.to_string())?;
+        user_metadata = processor.get_metadata(&trimmed).await.map_err(|e| e.to_string())?;
+        user_video_path = trimmed;
+    }
+
+    // every format gets checked, with more than one the messages say which
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    for format in &config.output_formats {
+        let check = format.preset().check(
+            user_metadata.duration,
+            config.dimensions_for(format),
+            &config.encoder_for(format),
+        );
+        let label = |message: String| {
+            if config.output_formats.len() > 1 { format!("{}: {}", format.name(), message) } else { message }
+        };
+        errors.extend(check.errors.into_iter().map(label));
+        warnings.extend(check.warnings.into_iter().map(label));
+    }
+
+    // imported subs win over transcribed ones for burn in
+    // theyre usually hand corrected so theyre the better text
+    // parsed here so a bad file fails the job before any downloading, line number and all
+    let imported_subs = match &config.subtitles_path {
+        Some(subs_path) => match subtitles::load_subtitles(Path::new(subs_path)) {
+            Ok(cues) => {
+                log::info!("loaded {} subtitle cues from {}", cues.len(), subs_path);
+                Some(cues)
+            }
+            Err(e) => {
+                errors.push(format!("{}: {}", subs_path, e));
+                None
+            }
+        },
+        None => None,
+    };
+
+    // same for a cue sheet, checking its times needs the video length from the probe above
+    let cue_sfx = match &config.sfx_cues {
+        Some(sheet) => {
+            let folder = config.sfx_folder.clone();
+            let library = tokio::task::spawn_blocking(move || folder.map(SfxLibrary::load_from_folder))
+                .await
+                .map_err(|e| e.to_string())?;
+            let categories = library.as_ref()
+                .map(|l| l.categories().clone())
+                .unwrap_or_else(|| SfxCategories::load(None));
+
+            let resolved = sfx_cues::load_cues(Path::new(sheet)).and_then(|cues| {
+                sfx_cues::validate(&cues, user_metadata.duration, &categories, library.as_ref())?;
+                Ok(sfx_cues::resolve(&cues, &categories, library.as_ref()))
+            });
+            match resolved {
+                Ok(sfx) => {
+                    log::info!("placed {} sfx from cue sheet", sfx.len());
+                    Some(sfx)
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}", sheet, e));
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    {
+        let mut mgr = manager.lock();
+        if config.output_formats.is_empty() {
+            errors.push("pick at least one output format".to_string());
+        }
+        if !errors.is_empty() {
+            let error = errors.join("; ");
+            mgr.set_failed(&job_id, error.clone());
+            return Err(error);
+        }
+        for warning in &warnings {
+            log::warn!("job {}: {}", job_id, warning);
+        }
+        mgr.add_warnings(&job_id, warnings);
+    }
+
     // ============================================
     // STAGE 1: GET BROLL VIDEOS
     // download from youtube OR load local files
@@ -420,8 +846,6 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
                 });
             }
             
-            let processor = Processor::new(&clips_dir).map_err(|e| e.to_string())?;
-            
             for (i, path_str) in paths.iter().enumerate() {
                 if check_cancelled() {
                     return Err("cancelled by user".to_string());
@@ -487,14 +911,71 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
         });
     }
 
-    // get the user's video duration so we know how much broll to make
-    let processor = Processor::new(&clips_dir).map_err(|e| e.to_string())?;
-    let user_video_path = PathBuf::from(&config.user_video_path);
-    let user_metadata = processor.get_metadata(&user_video_path)
-        .await
-        .map_err(|e| e.to_string())?;
-    
-    log::info!("user video duration: {:.1}s", user_metadata.duration);
+    // optional proxy pass - each source gets transcoded once to the job's size/fps
+    // with short gops, later jobs with the same file + settings reuse it
+    // previews skip it, making the proxy takes longer than the preview itself
+    if config.use_proxies && config.preview.is_none() {
+        let settings = config.proxy_settings(user_metadata.fps);
+        let cache = ProxyCache::new(ProxyCache::default_dir()).map_err(|e| e.to_string())?;
+        let total = downloaded_videos.len();
+
+        for (i, video) in downloaded_videos.iter_mut().enumerate() {
+            if check_cancelled() {
+                return Err("cancelled by user".to_string());
+            }
+            {
+                let mut mgr = manager.lock();
+                mgr.update_progress(&job_id, JobProgress {
+                    stage: "Preparing proxies...".to_string(),
+                    percent: 25.0 + (i as f32 / total as f32) * 2.0,
+                    current_item: Some(video.title.clone()),
+                    total_items: Some(total),
+                    completed_items: Some(i),
+                });
+            }
+
+            match cache.get_or_create(&processor, &video.path, settings).await {
+                Ok(proxy) => video.path = proxy,
+                Err(e) => {
+                    // the original still cuts fine, just slower
+                    log::warn!("proxy for {} failed, cutting the original: {}", video.title, e);
+                }
+            }
+        }
+    }
+
+    // optional reframe pass - only matters when the user video gets cropped into a region
+    // pip keeps the user full screen so theres nothing to track there
+    let uses_region = matches!(
+        config.overlay_position,
+        OverlayPosition::Top | OverlayPosition::Bottom | OverlayPosition::SideBySide
+    );
+    let user_crop: Option<CropPath> = if config.auto_reframe && uses_region {
+        {
+            let mut mgr = manager.lock();
+            mgr.update_progress(&job_id, JobProgress {
+                stage: "Finding you in the frame...".to_string(),
+                percent: 27.0,
+                current_item: None,
+                total_items: None,
+                completed_items: None,
+            });
+        }
+
+        match reframe::analyze(&processor, &user_video_path, &user_metadata, &ReframeConfig::default()).await {
+            Ok(path) => {
+                log::info!("reframe: {} keyframes", path.keyframes.len());
+                Some(path)
+            }
+            Err(e) => {
+                // not worth failing the job over, just fall back to the plain scale
+                log::warn!("reframe analysis failed, skipping: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
 
     // plan out the clips
     let scramble_config = ScrambleConfig {
@@ -502,9 +983,13 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
         min_clip_duration: config.min_clip_duration,
         duration_variance: 0.5,
         randomize_order: true,
+        frame_accurate: config.frame_accurate,
     };
     
-    let scrambler = Scrambler::new(scramble_config, &clips_dir).map_err(|e| e)?;
+    let mut scrambler = Scrambler::new(scramble_config, &clips_dir, processor.clone())?;
+    if config.preview.is_some() {
+        scrambler = scrambler.with_encoder(EncoderProfile::preview());
+    }
     let clip_specs = scrambler.plan_clips(&downloaded_videos, user_metadata.duration);
     
     {
@@ -518,7 +1003,7 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
         });
     }
     
-    let cut_clips = scrambler.cut_clips(&downloaded_videos, &clip_specs)
+    let cut_clips = scrambler.cut_clips(&downloaded_videos, &clip_specs, user_metadata.duration)
         .await
         .map_err(|e| e)?;
 
@@ -539,6 +1024,7 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
     // ============================================
     // STAGE 3: COMPOSITE FINAL VIDEO
     // combine broll with user video based on overlay settings
+    // everything above is shared, the render below runs once per output format
     // ============================================
     if check_cancelled() {
         return Err("cancelled by user".to_string());
@@ -556,68 +1042,319 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
         });
     }
 
-    // figure out output dimensions
-    let dimensions = if let OutputFormat::Custom = config.output_format {
-        Dimensions {
-            width: config.custom_width.unwrap_or(1920),
-            height: config.custom_height.unwrap_or(1080),
+    // captions - transcribe now so the .ass is ready for the composite pass
+    // one transcript for every format, only the placement differs
+    let caption_cues = match &config.captions {
+        Some(options) => {
+            {
+                let mut mgr = manager.lock();
+                mgr.update_progress(&job_id, JobProgress {
+                    stage: "Transcribing captions...".to_string(),
+                    percent: 77.0,
+                    current_item: None,
+                    total_items: None,
+                    completed_items: None,
+                });
+            }
+
+            let words = captions::transcribe(&processor, &user_video_path, &temp_base, options)
+                .await
+                .map_err(|e| e.to_string())?;
+            Some(captions::group_words(&words, options.max_words_per_cue, options.max_cue_duration))
         }
-    } else {
-        config.output_format.dimensions()
+        None => None,
     };
 
-    let final_output = output_dir.join(format!(
-        "godz_{}_{}.mp4",
-        config.output_format.name().to_lowercase(),
-        &job_id
-    ));
-    
-    // composite based on overlay position
-    match config.overlay_position {
-        OverlayPosition::Top => {
-            processor.composite_split_screen(
-                &broll_path,
-                &user_video_path,
-                &final_output,
-                dimensions,
-                config.split_ratio,
-            ).await.map_err(|e| e.to_string())?;
+    // a cue sheet means the user placed everything by hand, skip the auto placer
+    // the timeline is the same in every format so this only happens once
+    // the sheet itself was loaded + checked in the preflight
+    let mut sfx = Vec::new();
+    if let Some(placed) = cue_sfx {
+        sfx = placed;
+        let mut mgr = manager.lock();
+        mgr.set_sfx_events(&job_id, sfx.clone());
+    } else if let Some(folder) = config.sfx_folder.clone().filter(|_| config.sfx_placement.enabled) {
+        // otherwise sfx get auto placed on the broll cuts and the punchy bits of the voice
+        {
+            let mut mgr = manager.lock();
+            mgr.update_progress(&job_id, JobProgress {
+                stage: "Placing sound effects...".to_string(),
+                percent: 79.0,
+                current_item: None,
+                total_items: None,
+                completed_items: None,
+            });
         }
-        OverlayPosition::Bottom => {
-            processor.composite_split_screen(
-                &user_video_path,  // swap order - user on top
-                &broll_path,
-                &final_output,
-                dimensions,
-                1.0 - config.split_ratio,
-            ).await.map_err(|e| e.to_string())?;
+
+        // first scan of a folder listens to every file, keep that off the runtime
+        let library = tokio::task::spawn_blocking(move || SfxLibrary::load_from_folder(folder))
+            .await
+            .map_err(|e| e.to_string())?;
+        let voice = sfx_placement::analyze_voice_file(&processor, &user_video_path).await
+            .map_err(|e| e.to_string())?;
+        let boundaries = clip_boundaries(&cut_clips);
+
+        let events = sfx_placement::plan_events(
+            &library.available_categories(),
+            &boundaries,
+            &voice,
+            user_metadata.duration,
+            &config.sfx_placement,
+        );
+        sfx = library.resolve(&events);
+        log::info!("placed {} sfx", sfx.len());
+
+        let mut mgr = manager.lock();
+        mgr.set_sfx_events(&job_id, sfx.clone());
+    }
+
+    // fit to size spreads the budget over the whole file, so the bumpers count too
+    // transitions overlap a little, close enough for a bitrate
+    let mut bumper_duration = 0.0;
+    let fit_to_size = config.encoder.as_ref().is_some_and(|e| e.target_size_mb.is_some());
+    if let Some(bumpers) = config.bumpers.as_ref().filter(|_| fit_to_size) {
+        for source in [&bumpers.intro, &bumpers.outro].into_iter().flatten() {
+            bumper_duration += processor.get_metadata(&PathBuf::from(source)).await
+                .map_err(|e| e.to_string())?
+                .duration;
         }
-        OverlayPosition::TopLeft | OverlayPosition::TopRight |
-        OverlayPosition::BottomLeft | OverlayPosition::BottomRight => {
-            // picture in picture mode
-            processor.composite_pip(
-                &user_video_path,  // user is main video
-                &broll_path,       // broll is the overlay
-                &final_output,
-                dimensions,
-                config.overlay_position,
-                config.pip_scale,
-            ).await.map_err(|e| e.to_string())?;
+    }
+
+    let total = config.output_formats.len();
+    let mut finished: Vec<(PathBuf, f64)> = Vec::new();  // (video, where the main part starts)
+
+    for (index, format) in config.output_formats.iter().enumerate() {
+        if check_cancelled() {
+            return Err("cancelled by user".to_string());
+        }
+
+        // 80-95% gets split evenly between the formats
+        let progress = |stage: &str, fraction: f32| JobProgress {
+            stage: stage.to_string(),
+            percent: 80.0 + (index as f32 + fraction) * 15.0 / total as f32,
+            current_item: Some(format.name().to_string()),
+            total_items: Some(total),
+            completed_items: Some(index),
+        };
+
+        {
+            let mut mgr = manager.lock();
+            mgr.update_progress(&job_id, progress("Creating your final video...", 0.0));
+        }
+
+        let dimensions = config.dimensions_for(format);
+        let format_dir = temp_base.join(format.preset().value);
+        std::fs::create_dir_all(&format_dir).map_err(|e| e.to_string())?;
+
+        let final_output = output_dir.join(format!(
+            "godz_{}_{}.mp4",
+            format.preset().value,
+            &job_id
+        ));
+
+        let mut layers = CompositeLayers::default();
+
+        // logo sits in a corner that depends on the platform, see Watermark::default_placement
+        if let Some(watermark) = config.watermark.as_ref().filter(|w| !w.path.is_empty()) {
+            layers.watermark = Some(watermark.resolve(format, dimensions));
         }
-        OverlayPosition::SideBySide => {
-            processor.composite_side_by_side(
-                &broll_path,
-                &user_video_path,
-                &final_output,
-                dimensions,
-                config.split_ratio,
+
+        // text layers - title bar preset first so custom overlays draw over it
+        if let Some(title) = config.title.as_deref().filter(|t| !t.trim().is_empty()) {
+            layers.text_overlays.push(TextOverlay::title_bar(title));
+        }
+        layers.text_overlays.extend(config.text_overlays.iter().cloned());
+
+        if let Some(cues) = &imported_subs {
+            let placement = CaptionPlacement::for_style(&config.subtitle_style, config.overlay_position, dimensions, config.split_ratio);
+            let ass_path = format_dir.join("subtitles.ass");
+            std::fs::write(&ass_path, captions::to_ass(cues, &config.subtitle_style, placement, dimensions))
+                .map_err(|e| e.to_string())?;
+            layers.subtitles = Some(ass_path);
+        } else if let (Some(options), Some(cues)) = (&config.captions, &caption_cues) {
+            if options.burn_in {
+                let placement = CaptionPlacement::for_style(&options.style, config.overlay_position, dimensions, config.split_ratio);
+                let ass_path = format_dir.join("captions.ass");
+                std::fs::write(&ass_path, captions::to_ass(cues, &options.style, placement, dimensions))
+                    .map_err(|e| e.to_string())?;
+                layers.subtitles = Some(ass_path);
+            }
+        }
+        // fit to size needs the finished length, bumpers included
+        let mut encoder = config.encoder_for(format);
+        if encoder.target_size_mb.is_some() {
+            encoder = encoder.fit_to_duration(user_metadata.duration + bumper_duration);
+            log::info!("{}: fitting to {:.0} MB at {}kbps", format.name(),
+                encoder.target_size_mb.unwrap_or_default(), encoder.bitrate_kbps.unwrap_or_default());
+        }
+        let processor = processor
+            .clone()
+            .with_encoder(encoder.clone())
+            .with_layers(layers)
+            .with_voice_filter(config.mastering.voice_filters().filter(|_| config.mastering.enabled));
+
+        // the layout renders to a temp file, then each audio/bumper pass below
+        // takes the current file and hands back a new one. last one gets moved to final_output
+        let composite_output = format_dir.join("composite.mp4");
+        
+        // composite based on overlay position
+        let pip_style = config.pip_style_for(user_metadata.duration);
+        let (layout, [first, second]) = config.overlay_position.layout(
+            config.split_ratio,
+            &pip_style,
+            user_crop.as_ref(),
+            &broll_path,
+            &user_video_path,
+        );
+        match layout {
+            Layout::SplitScreen { ratio, crops } => {
+                processor.composite_split_screen(first, second, &composite_output, dimensions, ratio, crops)
+                    .await.map_err(|e| e.to_string())?;
+            }
+            Layout::Pip(style) => {
+                processor.composite_pip(first, second, &composite_output, dimensions, style)
+                    .await.map_err(|e| e.to_string())?;
+            }
+            Layout::SideBySide { ratio, crops } => {
+                processor.composite_side_by_side(first, second, &composite_output, dimensions, ratio, crops)
+                    .await.map_err(|e| e.to_string())?;
+            }
+        }
+
+        let mut current = composite_output;
+
+        // sfx and the music bed go under the voice in one mixing pass
+        if !sfx.is_empty() || config.music.is_some() {
+            {
+                let mut mgr = manager.lock();
+                mgr.update_progress(&job_id, progress("Mixing audio...", 0.4));
+            }
+
+            let mixed = format_dir.join("mixed.mp4");
+            processor.add_sfx(&current, &sfx, config.music.as_ref(), &mixed).await
+                .map_err(|e| e.to_string())?;
+            current = mixed;
+        }
+
+        // mastering runs on the full mix before bumpers get attached so they match the mastered level
+        // the voice cleanup already happened in the composite, this is just loudness + limiter
+        let mut loudness = None;
+        if config.mastering.enabled {
+            {
+                let mut mgr = manager.lock();
+                mgr.update_progress(&job_id, progress("Mastering audio...", 0.6));
+            }
+
+            let options = &config.mastering;
+            let measured = audio::measure_loudness(&current).await
+                .map_err(|e| e.to_string())?;
+
+            // silent or no audio = nothing to master, leave it alone
+            if let Some(before) = measured.filter(|l| !l.is_silent()) {
+                let target = options.target_for(format);
+                let mastered = format_dir.join("mastered.mp4");
+                processor.master_audio(&current, &mastered, &options.mastering_filter(&before, target)).await
+                    .map_err(|e| e.to_string())?;
+
+                if let Some(after) = audio::measure_loudness(&mastered).await.map_err(|e| e.to_string())? {
+                    log::info!("{}: loudness {:.1} -> {:.1} LUFS (target {:.1})", format.name(), before.integrated, after.integrated, target);
+                    loudness = Some(LoudnessReport { target_lufs: target, before, after });
+                }
+                current = mastered;
+            }
+        }
+
+        // intro/outro get conformed to the render and level matched to the voice
+        // so a hype intro doesnt blast people compared to the talking head
+        let mut main_offset = 0.0;
+        if let Some(bumpers) = config.bumpers.as_ref().filter(|b| !b.is_empty()) {
+            {
+                let mut mgr = manager.lock();
+                mgr.update_progress(&job_id, progress("Adding intro/outro...", 0.8));
+            }
+
+            let main_metadata = processor.get_metadata(&current).await
+                .map_err(|e| e.to_string())?;
+            let main_loudness = audio::measure_loudness(&current).await
+                .map_err(|e| e.to_string())?;
+
+            let mut conformed = Vec::new();
+            for (name, source) in [("intro", &bumpers.intro), ("outro", &bumpers.outro)] {
+                let Some(source) = source else {
+                    conformed.push(None);
+                    continue;
+                };
+                let source = PathBuf::from(source);
+
+                let loudness = audio::measure_loudness(&source).await
+                    .map_err(|e| format!("{} bumper: {}", name, e))?;
+                let gain = match (loudness, main_loudness) {
+                    (Some(clip), Some(main)) => audio::matching_gain_db(&clip, &main),
+                    _ => 0.0,
+                };
+                log::info!("{} bumper gain: {:.1}dB", name, gain);
+
+                let out = format_dir.join(format!("{}_conformed.mp4", name));
+                processor.conform_video(&source, &out, dimensions, main_metadata.fps, gain).await
+                    .map_err(|e| format!("{} bumper: {}", name, e))?;
+                conformed.push(Some(out));
+            }
+
+            let joined = format_dir.join("bumpered.mp4");
+            main_offset = processor.join_bumpers(
+                conformed[0].as_deref(),
+                &current,
+                conformed[1].as_deref(),
+                &joined,
+                bumpers.transition.as_ref(),
             ).await.map_err(|e| e.to_string())?;
+            current = joined;
         }
+
+        // temp_base and output_dir share a parent so this is a cheap rename
+        std::fs::rename(&current, &final_output).map_err(|e| e.to_string())?;
+        log::info!("{} render done: {}", format.name(), final_output.display());
+
+        // bitrate targets are a guess on short clips, so say so when it missed
+        let size_bytes = std::fs::metadata(&final_output).map(|m| m.len()).unwrap_or(0);
+        if let Some(target_mb) = encoder.target_size_mb {
+            let target_bytes = (target_mb * 1024.0 * 1024.0) as u64;
+            log::info!("{}: {} (target {:.0} MB)", format.name(), utils::format_file_size(size_bytes), target_mb);
+            if size_bytes > target_bytes {
+                let mut mgr = manager.lock();
+                mgr.add_warnings(&job_id, vec![format!(
+                    "{} came out at {}, over the {:.0} MB target",
+                    format.name(), utils::format_file_size(size_bytes), target_mb
+                )]);
+            }
+        }
+
+        // show it right away, the other formats can take a while
+        {
+            let mut mgr = manager.lock();
+            mgr.add_output(&job_id, JobOutput {
+                format: format.name().to_string(),
+                path: final_output.to_string_lossy().to_string(),
+                sidecar_files: Vec::new(),
+                loudness,
+                size_bytes,
+                encoder: processor.encoder_used().to_string(),
+            });
+        }
+        finished.push((final_output, main_offset));
+    }
+
+    // the renders share one fallback flag, so this only gets said once
+    if processor.fell_back_to_cpu() {
+        let mut mgr = manager.lock();
+        mgr.add_warnings(&job_id, vec![
+            "the hardware encoder failed partway, the rest was encoded on the cpu".to_string(),
+        ]);
     }
 
     // ============================================
     // STAGE 4: FINALIZE
-    // add sfx if provided and cleanup
+    // sidecars and cleanup
     // ============================================
     {
         let mut mgr = manager.lock();
@@ -631,15 +1368,36 @@ pub async fn run_job(manager: Arc<Mutex<JobManager>>, job_id: String) -> Result<
         });
     }
 
-    // TODO: add sfx if config.sfx_folder is set
-    // would use sfx.rs module here
+    // caption sidecars sit right next to each video
+    // shifted past the intro so they line up with the finished file
+    if let (Some(options), Some(cues)) = (&config.captions, &caption_cues) {
+        for (video, main_offset) in &finished {
+            let cues = captions::shift_cues(cues, *main_offset);
+            let written = captions::write_sidecars(&cues, video, options.export_srt, options.export_vtt)
+                .map_err(|e| e.to_string())?;
+            let mut mgr = manager.lock();
+            mgr.add_sidecar_files(&job_id, video, written);
+        }
+    }
 
     // mark complete
     {
         let mut mgr = manager.lock();
-        mgr.set_complete(&job_id, final_output.clone());
+        mgr.set_complete(&job_id);
     }
     
-    log::info!("job {} complete: {}", job_id, final_output.display());
+    log::info!("job {} complete: {} output(s)", job_id, finished.len());
     Ok(())
 }
+
+// where the broll cuts land on the output timeline
+// the first clip starts at 0 so thats not a cut
+fn clip_boundaries(clips: &[CutClip]) -> Vec<f64> {
+    clips
+        .iter()
+        .scan(0.0, |end, clip| {
+            *end += clip.duration;
+            Some(*end)
+        })
+        .collect()
+}
@@ -0,0 +1,460 @@
+// 1a. layers.rs - stuff that goes on top of the finished layout
+// 1b. every composite_* builds its layout, then these get stacked on the result
+// 1c. keeps captions, titles and friends out of each individual layout function
+
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::filters::{escape_filter_path, escape_filter_value, is_filter_color};
+use crate::jobs::OutputFormat;
+use crate::processor::Dimensions;
+
+// 2a. where a text overlay hangs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum TextAnchor {
+    #[default]
+    Free,       // use the overlay's own x/y
+    SplitLine,  // centered on the split in split screen layouts (falls back to y elsewhere)
+}
+
+// 2b. a piece of text drawn over the video for a while
+// x/y are the normalized center of the text, times are seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextOverlay {
+    pub text: String,
+    pub font_file: Option<String>,  // ttf/otf, None = system sans via fontconfig
+    pub font_size: u32,
+    pub color: String,              // "#rrggbb" or a color name, no alpha
+    pub box_color: Option<String>,  // background box behind the text, None = no box. same rules, box_opacity sets its alpha
+    pub box_opacity: f64,
+    pub box_padding: u32,
+    pub full_width_bar: bool,       // stretch the box across the whole frame
+    pub x: f64,
+    pub y: f64,
+    pub anchor: TextAnchor,
+    pub start: f64,
+    pub end: Option<f64>,           // None = until the video ends
+    pub fade: f64,                  // fade in/out length
+}
+
+impl Default for TextOverlay {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            font_file: None,
+            font_size: 72,
+            color: "#ffffff".to_string(),
+            box_color: None,
+            box_opacity: 0.6,
+            box_padding: 20,
+            full_width_bar: false,
+            x: 0.5,
+            y: 0.1,
+            anchor: TextAnchor::Free,
+            start: 0.0,
+            end: None,
+            fade: 0.3,
+        }
+    }
+}
+
+impl TextOverlay {
+    // 3a. title bar preset - full width band sitting on the split line
+    // the classic tiktok hook title between broll and face
+    pub fn title_bar(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            font_size: 64,
+            box_color: Some("#000000".to_string()),
+            box_opacity: 0.75,
+            box_padding: 24,
+            full_width_bar: true,
+            anchor: TextAnchor::SplitLine,
+            ..Self::default()
+        }
+    }
+
+    // 3b. colors go into the graph as is, so they get checked before a job starts
+    // alpha rides on box_opacity / the fades, an #rrggbbaa or name@0.5 here would double it up
+    pub fn check(&self) -> Result<(), String> {
+        for color in std::iter::once(&self.color).chain(self.box_color.as_ref()) {
+            if !is_filter_color(color, false) {
+                return Err(format!(
+                    "text overlay \"{}\": {:?} isnt a color - use #rrggbb or a name like white, box_opacity sets the alpha",
+                    self.text, color
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // 3c. drawtext (plus drawbox for bars that dont fade) for this overlay
+    // split_line is the normalized y of the split, if the layout has one
+    fn filters(&self, split_line: Option<f64>) -> Vec<String> {
+        let y = self.line_y(split_line);
+        let enable = self.enable_expr();
+
+        let mut filters = Vec::new();
+
+        // bars are a drawbox behind the text, sized off the font so it fits one line
+        // drawbox color is fixed so a fading bar goes through fading_bar instead
+        if let (true, Some(color)) = (self.full_width_bar && !self.fades(), &self.box_color) {
+            let bar_h = self.bar_height();
+            filters.push(format!(
+                "drawbox=x=0:y=ih*{:.4}-{}:w=iw:h={}:color={}@{:.2}:t=fill:enable='{}'",
+                y, bar_h / 2, bar_h, color, self.box_opacity.clamp(0.0, 1.0), enable
+            ));
+        }
+
+        let font = match &self.font_file {
+            Some(path) => format!("fontfile={}", escape_filter_path(std::path::Path::new(path))),
+            None => "font=Sans".to_string(),
+        };
+
+        let mut drawtext = format!(
+            "drawtext={}:text={}:expansion=none:fontsize={}:fontcolor={}:\
+             x='max(0,min(w-text_w,w*{:.4}-text_w/2))':y='max(0,min(h-text_h,h*{:.4}-text_h/2))':\
+             enable='{}'",
+            font,
+            escape_filter_value(&self.text),
+            self.font_size,
+            self.color,
+            self.x,
+            y,
+            enable
+        );
+
+        if let (false, Some(color)) = (self.full_width_bar, &self.box_color) {
+            drawtext.push_str(&format!(
+                ":box=1:boxcolor={}@{:.2}:boxborderw={}",
+                color, self.box_opacity.clamp(0.0, 1.0), self.box_padding
+            ));
+        }
+
+        if self.fades() {
+            drawtext.push_str(&format!(":alpha='{}'", self.fade_expr()));
+        }
+
+        filters.push(drawtext);
+        filters
+    }
+
+    // 3d. a full width bar that fades with its text
+    // a color source with an alpha fade, overlaid from label onto out - same timing as fade_expr
+    // only for overlays where has_fading_bar, the rest stay a plain drawbox
+    fn fading_bar(&self, split_line: Option<f64>, width: u32, label: &str, out: &str) -> String {
+        let color = self.box_color.as_deref().unwrap_or("black");
+        let bar_h = self.bar_height();
+
+        let mut source = format!(
+            "color=c={}@{:.2}:s={}x{}:r=30,format=rgba,fade=t=in:st={:.3}:d={:.3}:alpha=1",
+            color, self.box_opacity.clamp(0.0, 1.0), width, bar_h, self.start, self.fade
+        );
+        if let Some(end) = self.end {
+            source.push_str(&format!(",fade=t=out:st={:.3}:d={:.3}:alpha=1", (end - self.fade).max(self.start), self.fade));
+        }
+
+        // the color source never ends, shortest stops it with the video
+        format!(
+            "{}[{}_bar];[{}][{}_bar]overlay=x=0:y=H*{:.4}-{}:shortest=1:enable='{}'[{}]",
+            source, out, label, out, self.line_y(split_line), bar_h / 2, self.enable_expr(), out
+        )
+    }
+
+    fn has_fading_bar(&self) -> bool {
+        self.full_width_bar && self.box_color.is_some() && self.fades()
+    }
+
+    fn fades(&self) -> bool {
+        self.fade > 0.0
+    }
+
+    fn line_y(&self, split_line: Option<f64>) -> f64 {
+        match (self.anchor, split_line) {
+            (TextAnchor::SplitLine, Some(line)) => line,
+            _ => self.y,
+        }
+    }
+
+    fn bar_height(&self) -> u32 {
+        self.font_size + self.box_padding * 2
+    }
+
+    fn enable_expr(&self) -> String {
+        match self.end {
+            Some(end) => format!("between(t,{:.3},{:.3})", self.start, end),
+            None => format!("gte(t,{:.3})", self.start),
+        }
+    }
+
+    // fade in from start, fade out into end (if theres an end)
+    fn fade_expr(&self) -> String {
+        let fade_in = format!("clip((t-{:.3})/{:.3},0,1)", self.start, self.fade);
+        match self.end {
+            Some(end) => format!("{}*clip(({:.3}-t)/{:.3},0,1)", fade_in, end, self.fade),
+            None => fade_in,
+        }
+    }
+}
+
+// 4a. corners a watermark can sit in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WatermarkCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// 4b. logo watermark settings from the job
+// corner/margin left as None get the per-format defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watermark {
+    pub path: String,                     // png, alpha is respected
+    pub corner: Option<WatermarkCorner>,
+    pub scale: f64,                       // logo width as a fraction of output width
+    pub opacity: f64,                     // 0-1
+    pub margin: Option<f64>,              // fraction of the output width, same on both axes
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            corner: None,
+            scale: 0.12,
+            opacity: 0.85,
+            margin: None,
+        }
+    }
+}
+
+impl Watermark {
+    // 4c. default placement per platform
+    // margins are (x, y) fractions of the output size
+    // tiktok/reels stack buttons down the right and text along the bottom,
+    // plus the for you / following tabs along the top, so go top left under the tabs
+    // youtube puts its own branding + end screens bottom right
+    pub fn default_placement(format: &OutputFormat) -> (WatermarkCorner, f64, f64) {
+        match format {
+            OutputFormat::TikTok | OutputFormat::YouTubeShorts | OutputFormat::InstagramReels => {
+                (WatermarkCorner::TopLeft, 0.05, 0.11)
+            }
+            OutputFormat::InstagramFeed => (WatermarkCorner::TopRight, 0.04, 0.04),
+            OutputFormat::YouTube | OutputFormat::X => (WatermarkCorner::TopRight, 0.03, 0.04),
+            OutputFormat::Custom => (WatermarkCorner::BottomRight, 0.03, 0.03),
+        }
+    }
+
+    // 4d. turn the settings into pixels for a specific render
+    pub fn resolve(&self, format: &OutputFormat, target: Dimensions) -> WatermarkLayer {
+        let (default_corner, default_mx, default_my) = Self::default_placement(format);
+        let corner = self.corner.unwrap_or(default_corner);
+
+        // an explicit margin is the same on both sides, defaults can differ per axis
+        let (mx, my) = match self.margin {
+            Some(m) => (m, m * target.width as f64 / target.height.max(1) as f64),
+            None => (default_mx, default_my),
+        };
+
+        WatermarkLayer {
+            path: PathBuf::from(&self.path),
+            corner,
+            width: ((target.width as f64 * self.scale.clamp(0.01, 1.0)) as u32).max(2) / 2 * 2,
+            opacity: self.opacity.clamp(0.0, 1.0),
+            margin_x: (target.width as f64 * mx) as u32,
+            margin_y: (target.height as f64 * my) as u32,
+        }
+    }
+}
+
+// 4e. a watermark resolved for one output size
+#[derive(Debug, Clone)]
+pub struct WatermarkLayer {
+    pub path: PathBuf,
+    pub corner: WatermarkCorner,
+    pub width: u32,
+    pub opacity: f64,
+    pub margin_x: u32,
+    pub margin_y: u32,
+}
+
+impl WatermarkLayer {
+    // overlay x/y for the corner, W/H = video, w/h = logo
+    fn position(&self) -> (String, String) {
+        let left = self.margin_x.to_string();
+        let top = self.margin_y.to_string();
+        let right = format!("W-w-{}", self.margin_x);
+        let bottom = format!("H-h-{}", self.margin_y);
+
+        match self.corner {
+            WatermarkCorner::TopLeft => (left, top),
+            WatermarkCorner::TopRight => (right, top),
+            WatermarkCorner::BottomLeft => (left, bottom),
+            WatermarkCorner::BottomRight => (right, bottom),
+        }
+    }
+}
+
+// 5a. everything drawn over the layout
+// empty by default so plain jobs get the exact same graph as before
+#[derive(Debug, Clone, Default)]
+pub struct CompositeLayers {
+    pub watermark: Option<WatermarkLayer>,
+    pub text_overlays: Vec<TextOverlay>,
+    pub subtitles: Option<PathBuf>,  // .ass file burned in on top
+}
+
+// 5b. what attach hands back to the composite
+// extra_inputs need to be added as -i in order, right after the layout inputs
+pub struct LayeredGraph {
+    pub filter: String,
+    pub out_label: String,
+    pub extra_inputs: Vec<PathBuf>,
+}
+
+impl CompositeLayers {
+    pub fn is_empty(&self) -> bool {
+        self.watermark.is_none() && self.text_overlays.is_empty() && self.subtitles.is_none()
+    }
+
+    // 6a. hang the layer filters off the end of a layout graph
+    // takes the graph and the label its video ends on, the split line
+    // (normalized y) for layouts that have one, the output size and the index the next -i will get
+    pub fn attach(&self, graph: String, label: &str, split_line: Option<f64>, target: Dimensions, next_input: usize) -> LayeredGraph {
+        let mut graph = graph;
+        let mut label = label.to_string();
+        let mut extra_inputs = Vec::new();
+
+        // watermark goes first so titles and captions can sit over it
+        if let Some(wm) = &self.watermark {
+            let (x, y) = wm.position();
+            graph.push_str(&format!(
+                ";[{}:v]scale={}:-2,format=rgba,colorchannelmixer=aa={:.3}[wm];\
+                 [{}][wm]overlay=x={}:y={}[watermarked]",
+                next_input, wm.width, wm.opacity, label, x, y
+            ));
+            label = "watermarked".to_string();
+            extra_inputs.push(wm.path.clone());
+        }
+
+        let mut filters: Vec<String> = Vec::new();
+
+        // a fading bar is its own graph branch, so the text before it gets closed off first
+        for (i, overlay) in self.text_overlays.iter().filter(|o| !o.text.trim().is_empty()).enumerate() {
+            let out = format!("title{}", i);
+            if overlay.has_fading_bar() {
+                if !filters.is_empty() {
+                    graph.push_str(&format!(";[{}]{}[{}_under]", label, filters.join(","), out));
+                    label = format!("{}_under", out);
+                    filters.clear();
+                }
+                graph.push_str(&format!(";{}", overlay.fading_bar(split_line, target.width, &label, &out)));
+                label = out;
+            }
+            filters.extend(overlay.filters(split_line));
+        }
+
+        // subtitles last so captions never end up under a title
+        if let Some(path) = &self.subtitles {
+            filters.push(format!("subtitles=filename={}", escape_filter_path(path)));
+        }
+
+        if !filters.is_empty() {
+            graph.push_str(&format!(";[{}]{}[layered]", label, filters.join(",")));
+            label = "layered".to_string();
+        }
+
+        LayeredGraph {
+            filter: graph,
+            out_label: label,
+            extra_inputs,
+        }
+    }
+}
+
+// 6a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_layers_leave_graph_alone() {
+        let layered = CompositeLayers::default().attach("[0:v]null[out]".to_string(), "out", None, Dimensions::tiktok(), 2);
+        assert_eq!((layered.filter.as_str(), layered.out_label.as_str()), ("[0:v]null[out]", "out"));
+        assert!(layered.extra_inputs.is_empty());
+    }
+
+    #[test]
+    fn test_title_bar_on_split_line() {
+        let layers = CompositeLayers {
+            text_overlays: vec![TextOverlay { end: Some(3.0), fade: 0.0, ..TextOverlay::title_bar("wait for it") }],
+            ..CompositeLayers::default()
+        };
+        let layered = layers.attach("[a][b]vstack=inputs=2[out]".to_string(), "out", Some(0.4), Dimensions::tiktok(), 2);
+        let graph = layered.filter;
+        assert_eq!(layered.out_label, "layered");
+        assert!(graph.contains("[out]drawbox=x=0:y=ih*0.4000-56:w=iw:h=112:color=#000000@0.75:t=fill:enable='between(t,0.000,3.000)'"));
+        assert!(graph.contains("text=wait for it:expansion=none"));
+        assert!(graph.contains("h*0.4000-text_h/2"));
+        assert!(!graph.contains(":alpha="));
+        assert!(graph.ends_with("[layered]"));
+    }
+
+    #[test]
+    fn test_fading_title_bar_fades_with_the_text() {
+        let layers = CompositeLayers {
+            text_overlays: vec![
+                TextOverlay { text: "first".to_string(), ..TextOverlay::default() },
+                TextOverlay { end: Some(3.0), ..TextOverlay::title_bar("wait for it") },
+            ],
+            ..CompositeLayers::default()
+        };
+        let layered = layers.attach("[a][b]vstack=inputs=2[out]".to_string(), "out", Some(0.4), Dimensions::tiktok(), 2);
+        let graph = layered.filter;
+
+        // no drawbox, the bar is a faded color source under the text, after the text before it
+        assert!(!graph.contains("drawbox"));
+        assert!(graph.contains(";[out]drawtext=font=Sans:text=first"));
+        assert!(graph.contains("[title1_under];color=c=#000000@0.75:s=1080x112:r=30,format=rgba,\
+            fade=t=in:st=0.000:d=0.300:alpha=1,fade=t=out:st=2.700:d=0.300:alpha=1[title1_bar]"));
+        assert!(graph.contains("[title1_under][title1_bar]overlay=x=0:y=H*0.4000-56:shortest=1:enable='between(t,0.000,3.000)'[title1]"));
+        assert!(graph.contains(";[title1]drawtext=font=Sans:text=wait for it"));
+        assert!(graph.contains(":alpha='clip((t-0.000)/0.300,0,1)*clip((3.000-t)/0.300,0,1)'"));
+        assert_eq!(layered.out_label, "layered");
+        assert!(graph.ends_with("[layered]"));
+    }
+
+    #[test]
+    fn test_overlay_colors_are_checked() {
+        assert!(TextOverlay::title_bar("ok").check().is_ok());
+
+        let hostile = TextOverlay { color: "white:x=0,drawbox".to_string(), ..TextOverlay::title_bar("hi") };
+        assert!(hostile.check().is_err());
+
+        // alpha belongs in box_opacity, #000000@0.5@0.75 wont parse
+        for box_color in ["#000000@0.5", "#00000080", "black];[x"] {
+            let overlay = TextOverlay { box_color: Some(box_color.to_string()), ..TextOverlay::title_bar("hi") };
+            assert!(overlay.check().unwrap_err().contains("box_opacity"), "{}", box_color);
+        }
+    }
+
+    #[test]
+    fn test_watermark_defaults_per_format() {
+        let wm = Watermark { path: "logo.png".to_string(), ..Watermark::default() };
+
+        let layer = wm.resolve(&OutputFormat::TikTok, Dimensions::tiktok());
+        assert_eq!((layer.corner, layer.width, layer.margin_x, layer.margin_y), (WatermarkCorner::TopLeft, 128, 54, 211));
+
+        let layer = wm.resolve(&OutputFormat::YouTube, Dimensions::youtube());
+        assert_eq!(layer.corner, WatermarkCorner::TopRight);
+
+        let layers = CompositeLayers { watermark: Some(layer), ..CompositeLayers::default() };
+        let layered = layers.attach("[a][b]hstack=inputs=2[out]".to_string(), "out", None, Dimensions::youtube(), 2);
+        assert_eq!(layered.out_label, "watermarked");
+        assert_eq!(layered.extra_inputs, vec![PathBuf::from("logo.png")]);
+        assert!(layered.filter.contains("[2:v]scale=230:-2,format=rgba,colorchannelmixer=aa=0.850[wm]"));
+        assert!(layered.filter.contains("[out][wm]overlay=x=W-w-57:y=43[watermarked]"));
+    }
+}
@@ -14,90 +14,242 @@ mod processor;
 mod scrambler;
 mod jobs;
 mod sfx;
+mod sfx_analysis;
+mod sfx_categories;
+mod sfx_placement;
+mod sfx_cues;
 mod utils;
 mod setup;
-
-use jobs::{JobManager, JobStatus, JobConfig, OutputFormat, BrollSource, OverlayPosition};
+mod filters;
+mod reframe;
+mod pip;
+mod layers;
+mod captions;
+mod subtitles;
+mod audio;
+mod bumpers;
+mod encoder;
+mod hwaccel;
+mod proxy;
+mod capabilities;
+mod presets;
+
+use jobs::{JobManager, JobStatus, JobConfig, JobState, OutputFormat, BrollSource, OverlayPosition, PreviewOptions};
+use pip::PipStyle;
+use captions::{CaptionOptions, CaptionStyle};
+use layers::{TextOverlay, Watermark};
+use bumpers::Bumpers;
+use encoder::EncoderProfile;
+use presets::ExportPreset;
+use audio::{MasteringOptions, MusicBed};
+use sfx::{SfxFileInfo, SfxLibrary, SfxManifest, SfxPlacement};
+use sfx_placement::AutoPlacement;
+use sfx_cues::SfxCue;
+use processor::Processor;
+use capabilities::Capabilities;
+use sfx_categories::{SfxCategories, SfxCategory};
 use std::sync::Arc;
 use parking_lot::Mutex;
-use tauri::State;
+use tauri::{Manager, State};
+use tokio::sync::RwLock;
 
 // 2a. shared app state
 // job_manager handles all the video processing jobs
 // wrapped in Arc<Mutex> so multiple threads can access it
+// capabilities = what the installed ffmpeg supports, probed once at startup
+// the probe holds the write lock until its done, so anything reading it waits for the result
 struct AppState {
     job_manager: Arc<Mutex<JobManager>>,
+    previews: Arc<Mutex<JobManager>>,  // preview renders, kept out of the jobs list
+    capabilities: Arc<RwLock<Option<Arc<Capabilities>>>>,
+}
+
+impl AppState {
+    // the probe result, after waiting for the startup probe if its still going
+    async fn capabilities(&self) -> Arc<Capabilities> {
+        self.capabilities.read().await.clone().unwrap_or_else(|| Arc::new(Capabilities::missing()))
+    }
 }
 
 // ============================================
 // TAURI COMMANDS - frontend calls these
 // ============================================
 
-// 3a. start a new job - the main function users care about
-// takes all the settings and kicks off processing
-#[tauri::command]
-async fn start_job(
-    state: State<'_, AppState>,
+// 3a. everything a job gets started with, straight from the frontend
+// start_job and render_preview both take one of these
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobRequest {
+    #[serde(default)]
     youtube_links: Vec<String>,
     local_broll_paths: Option<Vec<String>>,
     user_video_path: String,
     output_format: String,
+    output_formats: Option<Vec<String>>,
     overlay_position: Option<String>,
     custom_width: Option<u32>,
     custom_height: Option<u32>,
     split_ratio: Option<f64>,
     pip_scale: Option<f64>,
     sfx_folder: Option<String>,
-) -> Result<String, String> {
-    // parse output format from string
-    let format = match output_format.to_lowercase().as_str() {
-        "youtube" => OutputFormat::YouTube,
-        "tiktok" => OutputFormat::TikTok,
-        "instagram" => OutputFormat::Instagram,
-        "custom" => OutputFormat::Custom,
-        _ => OutputFormat::YouTube,
-    };
+    auto_reframe: Option<bool>,
+    pip_style: Option<PipStyle>,
+    captions: Option<CaptionOptions>,
+    subtitles_path: Option<String>,
+    subtitle_style: Option<CaptionStyle>,
+    title: Option<String>,
+    text_overlays: Option<Vec<TextOverlay>>,
+    watermark: Option<Watermark>,
+    bumpers: Option<Bumpers>,
+    mastering: Option<MasteringOptions>,
+    music: Option<MusicBed>,
+    sfx_placement: Option<AutoPlacement>,
+    sfx_cues: Option<String>,
+    encoder: Option<EncoderProfile>,
+    use_proxies: Option<bool>,
+    frame_accurate: Option<bool>,
+}
 
-    // parse overlay position
-    let position = match overlay_position.as_deref() {
-        Some("top") => OverlayPosition::Top,
-        Some("bottom") => OverlayPosition::Bottom,
-        Some("topleft") | Some("top-left") => OverlayPosition::TopLeft,
-        Some("topright") | Some("top-right") => OverlayPosition::TopRight,
-        Some("bottomleft") | Some("bottom-left") => OverlayPosition::BottomLeft,
-        Some("bottomright") | Some("bottom-right") => OverlayPosition::BottomRight,
-        Some("sidebyside") | Some("side-by-side") => OverlayPosition::SideBySide,
-        _ => OverlayPosition::Top,
-    };
+impl JobRequest {
+    // parse + check everything up front so mistakes fail before any work starts
+    fn into_config(self) -> Result<JobConfig, String> {
+        let JobRequest {
+            youtube_links,
+            local_broll_paths,
+            user_video_path,
+            output_format,
+            output_formats,
+            overlay_position,
+            custom_width,
+            custom_height,
+            split_ratio,
+            pip_scale,
+            sfx_folder,
+            auto_reframe,
+            pip_style,
+            captions,
+            subtitles_path,
+            subtitle_style,
+            title,
+            text_overlays,
+            watermark,
+            bumpers,
+            mastering,
+            music,
+            sfx_placement,
+            sfx_cues,
+            encoder,
+            use_proxies,
+            frame_accurate,
+        } = self;
+
+        // parse output formats from strings
+        // output_formats = render all of them from the same broll, output_format is the single one
+        let mut formats: Vec<OutputFormat> = Vec::new();
+        for value in output_formats.filter(|f| !f.is_empty()).unwrap_or_else(|| vec![output_format]) {
+            let format = OutputFormat::from_value(&value);
+            // asking for the same platform twice would just overwrite the first render
+            if !formats.iter().any(|f| f.name() == format.name()) {
+                formats.push(format);
+            }
+        }
 
-    // figure out broll source - local files take priority
-    let broll_source = if let Some(paths) = local_broll_paths {
-        if !paths.is_empty() {
-            BrollSource::LocalFiles(paths)
+        // parse overlay position
+        let position = OverlayPosition::from_value(overlay_position.as_deref().unwrap_or("top"));
+
+        // figure out broll source - local files take priority
+        let broll_source = if let Some(paths) = local_broll_paths {
+            if !paths.is_empty() {
+                BrollSource::LocalFiles(paths)
+            } else if !youtube_links.is_empty() {
+                BrollSource::YouTube(youtube_links)
+            } else {
+                return Err("yo you need to provide some broll - either youtube links or local files".to_string());
+            }
         } else if !youtube_links.is_empty() {
             BrollSource::YouTube(youtube_links)
         } else {
             return Err("yo you need to provide some broll - either youtube links or local files".to_string());
+        };
+
+        // captions need whisper.cpp and a model, find out now instead of after the downloads
+        if let Some(options) = &captions {
+            if setup::find_whisper_binary().is_none() {
+                return Err("captions need whisper.cpp - install it (brew install whisper-cpp) or put whisper-cli in the godz bin folder".to_string());
+            }
+            // a model path has to exist, a model name has to be one we can download
+            if !std::path::Path::new(&options.model).exists() {
+                setup::whisper_model_path(&options.model)?;
+            }
         }
-    } else if !youtube_links.is_empty() {
-        BrollSource::YouTube(youtube_links)
-    } else {
-        return Err("yo you need to provide some broll - either youtube links or local files".to_string());
-    };
 
-    let config = JobConfig {
-        broll_source,
-        user_video_path,
-        output_format: format,
-        sfx_folder,
-        max_clip_duration: 4.0,
-        min_clip_duration: 1.5,
-        overlay_position: position,
-        custom_width,
-        custom_height,
-        split_ratio: split_ratio.unwrap_or(0.5),
-        pip_scale: pip_scale.unwrap_or(0.3),
-    };
+        // same deal for the logo, a missing file should fail before any downloading
+        if let Some(wm) = &watermark {
+            if !wm.path.is_empty() && !std::path::Path::new(&wm.path).exists() {
+                return Err(format!("watermark image not found: {}", wm.path));
+            }
+        }
+        for overlay in text_overlays.iter().flatten() {
+            overlay.check()?;
+        }
+        if let Some(b) = &bumpers {
+            for path in b.intro.iter().chain(b.outro.iter()) {
+                if !std::path::Path::new(path).exists() {
+                    return Err(format!("bumper video not found: {}", path));
+                }
+            }
+        }
+        if let Some(bed) = &music {
+            if !std::path::Path::new(&bed.path).exists() {
+                return Err(format!("music track not found: {}", bed.path));
+            }
+        }
+
+        Ok(JobConfig {
+            broll_source,
+            user_video_path,
+            output_formats: formats,
+            sfx_folder,
+            max_clip_duration: 4.0,
+            min_clip_duration: 1.5,
+            overlay_position: position,
+            custom_width,
+            custom_height,
+            split_ratio: split_ratio.unwrap_or(0.5),
+            pip_scale: pip_scale.unwrap_or(0.3),
+            auto_reframe: auto_reframe.unwrap_or(false),
+            pip_style,
+            captions,
+            subtitles_path,
+            subtitle_style: subtitle_style.unwrap_or_default(),
+            title,
+            text_overlays: text_overlays.unwrap_or_default(),
+            watermark,
+            bumpers,
+            mastering: mastering.unwrap_or_default(),
+            music,
+            sfx_placement: sfx_placement.unwrap_or_default(),
+            sfx_cues,
+            encoder,  // None = each platform's recommended settings
+            preview: None,
+            use_proxies: use_proxies.unwrap_or(false),
+            frame_accurate: frame_accurate.unwrap_or(false),
+        })
+    }
+}
+
+// 3b. start a new job - the main function users care about
+// takes all the settings and kicks off processing
+#[tauri::command]
+async fn start_job(state: State<'_, AppState>, request: JobRequest) -> Result<String, String> {
+    let config = request.into_config()?;
+
+    // features this ffmpeg build cant do fail here instead of halfway through a render
+    let capabilities = state.capabilities().await;
+    let unsupported = capabilities.unsupported(&config);
+    if !unsupported.is_empty() {
+        return Err(unsupported.join("; "));
+    }
 
     // create the job and get its id
     let job_id = {
@@ -109,14 +261,132 @@ async fn start_job(
     let manager = Arc::clone(&state.job_manager);
     let id_clone = job_id.clone();
     tokio::spawn(async move {
-        if let Err(e) = jobs::run_job(manager, id_clone.clone()).await {
+        if let Err(e) = jobs::run_job(Arc::clone(&manager), id_clone.clone(), capabilities).await {
             log::error!("job {} failed: {}", id_clone, e);
+            manager.lock().fail_unfinished(&id_clone, e);
         }
     });
 
     Ok(job_id)
 }
 
+// 3c. quick low res render of the first few seconds, to check the layout before a full export
+// same request and pipeline as start_job, but awaited here and never in the jobs list
+// returns the preview video path
+#[tauri::command]
+async fn render_preview(state: State<'_, AppState>, request: JobRequest, seconds: Option<f64>) -> Result<String, String> {
+    let options = PreviewOptions {
+        seconds: seconds.unwrap_or(10.0).clamp(1.0, 60.0),
+        ..PreviewOptions::default()
+    };
+    let config = request.into_config()?.into_preview(options);
+
+    let capabilities = state.capabilities().await;
+    let unsupported = capabilities.unsupported(&config);
+    if !unsupported.is_empty() {
+        return Err(unsupported.join("; "));
+    }
+
+    // only the newest preview matters - stop any still rendering, clean up the old files
+    // removing a job reads as cancelled to its run_job, a running one cleans up its own folder below
+    let job_id = {
+        let mut previews = state.previews.lock();
+        for old in previews.get_all_jobs() {
+            previews.remove_job(&old.id);
+            if matches!(old.state, JobState::Complete | JobState::Failed | JobState::Cancelled) {
+                let _ = std::fs::remove_dir_all(std::env::temp_dir().join("godz").join(&old.id));
+            }
+        }
+        previews.create_job(config)
+    };
+
+    let result = jobs::run_job(Arc::clone(&state.previews), job_id.clone(), capabilities).await;
+
+    // gone from the list = a newer preview replaced this one while it rendered
+    let output = state.previews.lock()
+        .get_job_status(&job_id)
+        .and_then(|s| s.outputs.into_iter().next())
+        .map(|output| output.path);
+
+    match (result, output) {
+        (Ok(()), Some(path)) => Ok(path),
+        (result, _) => {
+            // nothing will ever show this one, dont leave its files behind
+            let error = result.err().unwrap_or_else(|| "preview didnt produce a video".to_string());
+            state.previews.lock().fail_unfinished(&job_id, error.clone());
+            let _ = std::fs::remove_dir_all(std::env::temp_dir().join("godz").join(&job_id));
+            Err(error)
+        }
+    }
+}
+
+// 3d. one frame of the layout as a png, for live feedback while dragging the layout sliders
+// same layout + graph builders as the real composite, at preview size and without layers
+// broll wraps around if its shorter than the timestamp
+#[tauri::command]
+async fn render_layout_still(
+    state: State<'_, AppState>,
+    user_video_path: String,
+    broll_path: String,
+    timestamp: f64,
+    output_format: String,
+    overlay_position: Option<String>,
+    split_ratio: Option<f64>,
+    pip_scale: Option<f64>,
+    pip_style: Option<PipStyle>,
+    custom_width: Option<u32>,
+    custom_height: Option<u32>,
+) -> Result<String, String> {
+    let format = OutputFormat::from_value(&output_format);
+    let config = JobConfig {
+        output_formats: vec![format.clone()],
+        overlay_position: OverlayPosition::from_value(overlay_position.as_deref().unwrap_or("top")),
+        split_ratio: split_ratio.unwrap_or(0.5),
+        pip_scale: pip_scale.unwrap_or(0.3),
+        pip_style,
+        custom_width,
+        custom_height,
+        preview: Some(PreviewOptions::default()),
+        ..JobConfig::default()
+    };
+
+    let stills_dir = std::env::temp_dir().join("godz").join("stills");
+    let processor = Processor::new(&stills_dir, &state.capabilities().await).map_err(|e| e.to_string())?;
+    let user = std::path::PathBuf::from(&user_video_path);
+    let broll = std::path::PathBuf::from(&broll_path);
+    let user_meta = processor.get_metadata(&user).await.map_err(|e| e.to_string())?;
+    let broll_meta = processor.get_metadata(&broll).await.map_err(|e| e.to_string())?;
+
+    // slider drags fire a lot of these, clear out the ones the ui already showed
+    if let Ok(entries) = std::fs::read_dir(&stills_dir) {
+        for entry in entries.flatten() {
+            let stale = entry.metadata().ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age.as_secs() > 30);
+            if stale {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    let pip_style = config.pip_style_for(user_meta.duration);
+    let (layout, inputs) = config.overlay_position.layout(config.split_ratio, &pip_style, None, &broll, &user);
+    let seek_in = |duration: f64| {
+        let t = if duration > 0.0 { timestamp.max(0.0) % duration } else { 0.0 };
+        t.min((duration - 0.1).max(0.0))
+    };
+    let (user_t, broll_t) = (seek_in(user_meta.duration), seek_in(broll_meta.duration));
+    let seeks = inputs.map(|input| if input == broll.as_path() { broll_t } else { user_t });
+
+    let output = stills_dir.join(format!("still_{}.png", uuid::Uuid::new_v4()));
+    processor
+        .render_still(&layout, inputs, seeks, user_t, config.dimensions_for(&format), &output)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(output.to_string_lossy().to_string())
+}
+
 // 4a. get status of a specific job
 // frontend polls this to update the ui
 #[tauri::command]
@@ -141,22 +411,46 @@ fn cancel_job(state: State<'_, AppState>, job_id: String) -> Result<(), String>
     manager.cancel_job(&job_id)
 }
 
+// 4d. empty the broll proxy cache, returns the bytes freed
+#[tauri::command]
+fn clear_proxy_cache() -> Result<u64, String> {
+    let cache = proxy::ProxyCache::new(proxy::ProxyCache::default_dir()).map_err(|e| e.to_string())?;
+    cache.clear().map_err(|e| e.to_string())
+}
+
 // 5a. check if ffmpeg and yt-dlp are installed
 // we need both for the app to work properly
 #[tauri::command]
-fn check_dependencies() -> Result<DependencyStatus, String> {
-    let ffmpeg = which::which("ffmpeg").is_ok();
+async fn check_dependencies(state: State<'_, AppState>) -> Result<DependencyStatus, String> {
+    // startup probe is reused, unless ffmpeg was missing then - maybe they installed it since
+    let mut caps = state.capabilities().await;
+    if !caps.ffmpeg_installed() {
+        caps = Arc::new(
+            tokio::task::spawn_blocking(Capabilities::probe)
+                .await
+                .map_err(|e| e.to_string())?,
+        );
+        *state.capabilities.write().await = Some(Arc::clone(&caps));
+    }
+    let ffmpeg = caps.ffmpeg_installed();
     // use our setup module which checks PATH and app data dir
     let ytdlp = setup::check_ytdlp_available();
     
-    // detect gpu encoder availability
-    let gpu_encoder = detect_gpu_encoder_name();
+    // the gpu encoder that passed its test encode
+    let gpu_encoder = caps.gpu_encoder_name();
+
+    // whisper.cpp is optional, only captions need it
+    let whisper = setup::find_whisper_binary().is_some();
     
     Ok(DependencyStatus {
         ffmpeg_installed: ffmpeg,
         ytdlp_installed: ytdlp,
+        whisper_installed: whisper,
         all_good: ffmpeg,  // only ffmpeg is truly required, yt-dlp can be skipped with local mode
         gpu_encoder,
+        ffmpeg_version: caps.ffmpeg_version.clone(),
+        missing_filters: caps.missing_filters().iter().map(|f| f.to_string()).collect(),
+        hwaccels: caps.hwaccels.clone(),
     })
 }
 
@@ -168,12 +462,24 @@ async fn download_ytdlp() -> Result<String, String> {
     Ok(path.to_string_lossy().to_string())
 }
 
+// 5c. download a whisper model for captions
+// model is a ggml name like "base" or "small.en"
+#[tauri::command]
+async fn download_whisper_model(model: Option<String>) -> Result<String, String> {
+    let path = setup::ensure_whisper_model(model.as_deref().unwrap_or("base")).await?;
+    Ok(path.to_string_lossy().to_string())
+}
+
 #[derive(serde::Serialize)]
 struct DependencyStatus {
     ffmpeg_installed: bool,
     ytdlp_installed: bool,
+    whisper_installed: bool,
     all_good: bool,
     gpu_encoder: String,
+    ffmpeg_version: Option<String>,
+    missing_filters: Vec<String>,  // features needing these get refused at start_job
+    hwaccels: Vec<String>,
 }
 
 // 5b. validate youtube url before we try downloading
@@ -219,16 +525,19 @@ struct AppDirs {
 }
 
 // 7a. get available overlay positions for ui dropdown
+// pip entries carry their default style so the ui can start from it
 #[tauri::command]
 fn get_overlay_positions() -> Vec<OverlayOption> {
+    let pip_default = |position| Some(PipStyle::for_corner(position, 0.3));
+
     vec![
-        OverlayOption { value: "top".to_string(), label: "B-Roll on Top".to_string(), description: "Classic split - B-Roll above, you below".to_string() },
-        OverlayOption { value: "bottom".to_string(), label: "B-Roll on Bottom".to_string(), description: "Split - you above, B-Roll below".to_string() },
-        OverlayOption { value: "top-left".to_string(), label: "Picture in Picture (Top Left)".to_string(), description: "Small B-Roll overlay in top left corner".to_string() },
-        OverlayOption { value: "top-right".to_string(), label: "Picture in Picture (Top Right)".to_string(), description: "Small B-Roll overlay in top right corner".to_string() },
-        OverlayOption { value: "bottom-left".to_string(), label: "Picture in Picture (Bottom Left)".to_string(), description: "Small B-Roll overlay in bottom left corner".to_string() },
-        OverlayOption { value: "bottom-right".to_string(), label: "Picture in Picture (Bottom Right)".to_string(), description: "Small B-Roll overlay in bottom right corner".to_string() },
-        OverlayOption { value: "side-by-side".to_string(), label: "Side by Side".to_string(), description: "B-Roll on left, you on right".to_string() },
+        OverlayOption { value: "top".to_string(), label: "B-Roll on Top".to_string(), description: "Classic split - B-Roll above, you below".to_string(), kind: "split".to_string(), pip_style: None },
+        OverlayOption { value: "bottom".to_string(), label: "B-Roll on Bottom".to_string(), description: "Split - you above, B-Roll below".to_string(), kind: "split".to_string(), pip_style: None },
+        OverlayOption { value: "top-left".to_string(), label: "Picture in Picture (Top Left)".to_string(), description: "Small B-Roll overlay in top left corner".to_string(), kind: "pip".to_string(), pip_style: pip_default(OverlayPosition::TopLeft) },
+        OverlayOption { value: "top-right".to_string(), label: "Picture in Picture (Top Right)".to_string(), description: "Small B-Roll overlay in top right corner".to_string(), kind: "pip".to_string(), pip_style: pip_default(OverlayPosition::TopRight) },
+        OverlayOption { value: "bottom-left".to_string(), label: "Picture in Picture (Bottom Left)".to_string(), description: "Small B-Roll overlay in bottom left corner".to_string(), kind: "pip".to_string(), pip_style: pip_default(OverlayPosition::BottomLeft) },
+        OverlayOption { value: "bottom-right".to_string(), label: "Picture in Picture (Bottom Right)".to_string(), description: "Small B-Roll overlay in bottom right corner".to_string(), kind: "pip".to_string(), pip_style: pip_default(OverlayPosition::BottomRight) },
+        OverlayOption { value: "side-by-side".to_string(), label: "Side by Side".to_string(), description: "B-Roll on left, you on right".to_string(), kind: "side".to_string(), pip_style: None },
     ]
 }
 
@@ -237,50 +546,124 @@ struct OverlayOption {
     value: String,
     label: String,
     description: String,
+    kind: String,                  // split, pip or side
+    pip_style: Option<PipStyle>,   // default styling for pip entries
 }
 
 // 7b. get output format presets
+// full platform rules so the ui can show limits next to each one
 #[tauri::command]
-fn get_output_formats() -> Vec<FormatOption> {
-    vec![
-        FormatOption { value: "youtube".to_string(), label: "YouTube".to_string(), width: 1920, height: 1080, description: "16:9 landscape for YouTube".to_string() },
-        FormatOption { value: "tiktok".to_string(), label: "TikTok".to_string(), width: 1080, height: 1920, description: "9:16 portrait for TikTok/Reels".to_string() },
-        FormatOption { value: "instagram".to_string(), label: "Instagram".to_string(), width: 1080, height: 1350, description: "4:5 for Instagram feed".to_string() },
-        FormatOption { value: "custom".to_string(), label: "Custom".to_string(), width: 0, height: 0, description: "Pick your own dimensions".to_string() },
-    ]
+fn get_output_formats() -> Vec<ExportPreset> {
+    ExportPreset::all()
 }
 
-#[derive(serde::Serialize)]
-struct FormatOption {
-    value: String,
-    label: String,
-    width: u32,
-    height: u32,
-    description: String,
+// 8a. scan an sfx folder and show what every file got sorted into
+// first scan of a big pack runs the classifier on everything, so keep it off the async threads
+#[tauri::command]
+async fn scan_sfx_library(folder: String) -> Result<Vec<SfxFileInfo>, String> {
+    tokio::task::spawn_blocking(move || SfxLibrary::load_from_folder(&folder).files().to_vec())
+        .await
+        .map_err(|e| e.to_string())
 }
 
-// helper to get gpu encoder name for status display
-fn detect_gpu_encoder_name() -> String {
-    if check_encoder("h264_nvenc") {
-        "NVIDIA NVENC (GPU)".to_string()
-    } else if check_encoder("h264_vaapi") {
-        "AMD/Intel VAAPI (GPU)".to_string()
-    } else if check_encoder("h264_videotoolbox") {
-        "Apple VideoToolbox (GPU)".to_string()
-    } else {
-        "CPU (libx264)".to_string()
+// 8b. user override for one file, saved in the folder's manifest
+// category None + excluded false puts it back on automatic
+#[tauri::command]
+fn set_sfx_assignment(folder: String, file: String, category: Option<String>, excluded: Option<bool>) -> Result<(), String> {
+    let folder = std::path::Path::new(&folder);
+    if let Some(name) = &category {
+        if SfxCategories::load(Some(folder)).get(name).is_none() {
+            return Err(format!("theres no sfx category called '{}'", name));
+        }
     }
+
+    SfxManifest::set_override(
+        folder,
+        std::path::Path::new(&file),
+        category,
+        excluded.unwrap_or(false),
+    ).map_err(|e| format!("couldnt save sfx manifest: {}", e))
 }
 
-fn check_encoder(encoder: &str) -> bool {
-    std::process::Command::new("ffmpeg")
-        .args(["-hide_banner", "-encoders"])
-        .output()
-        .map(|output| {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            stdout.contains(encoder)
-        })
-        .unwrap_or(false)
+// 8c. every sfx category (built-in + sfx_categories.json) for the ui
+// pass the sfx folder to include that library's own categories
+#[tauri::command]
+fn get_sfx_categories(folder: Option<String>) -> Vec<SfxCategory> {
+    SfxCategories::load(folder.as_deref().map(std::path::Path::new))
+        .all()
+        .to_vec()
+}
+
+// 8d. preview where auto placement would put sfx, before rendering anything
+// real broll cuts only exist once the job cuts clips, so pass clip_boundaries
+// if you have them, otherwise cuts are estimated at the average clip length
+#[tauri::command]
+async fn preview_sfx_placement(
+    state: State<'_, AppState>,
+    user_video_path: String,
+    sfx_folder: String,
+    clip_boundaries: Option<Vec<f64>>,
+    clip_length: Option<f64>,
+    placement: Option<AutoPlacement>,
+) -> Result<Vec<SfxPlacement>, String> {
+    let processor = Processor::new(std::env::temp_dir().join("godz").join("preview"), &state.capabilities().await)
+        .map_err(|e| e.to_string())?;
+    let video = std::path::Path::new(&user_video_path);
+    let duration = processor.get_metadata(video).await.map_err(|e| e.to_string())?.duration;
+
+    let library = tokio::task::spawn_blocking(move || SfxLibrary::load_from_folder(sfx_folder))
+        .await
+        .map_err(|e| e.to_string())?;
+    let voice = sfx_placement::analyze_voice_file(&processor, video).await
+        .map_err(|e| e.to_string())?;
+
+    let boundaries = clip_boundaries
+        .unwrap_or_else(|| sfx_placement::estimate_boundaries(duration, clip_length.unwrap_or(2.75)));
+    let events = sfx_placement::plan_events(
+        &library.available_categories(),
+        &boundaries,
+        &voice,
+        duration,
+        &placement.unwrap_or_default(),
+    );
+
+    Ok(library.resolve(&events))
+}
+
+// 8e. load + fully check a cue sheet against the user video
+// returns the cues so the ui can show them on the timeline
+#[tauri::command]
+async fn validate_sfx_cues(
+    state: State<'_, AppState>,
+    path: String,
+    user_video_path: String,
+    sfx_folder: Option<String>,
+) -> Result<Vec<SfxCue>, String> {
+    let cues = sfx_cues::load_cues(std::path::Path::new(&path)).map_err(|e| e.to_string())?;
+
+    let processor = Processor::new(std::env::temp_dir().join("godz").join("preview"), &state.capabilities().await)
+        .map_err(|e| e.to_string())?;
+    let duration = processor.get_metadata(std::path::Path::new(&user_video_path)).await
+        .map_err(|e| e.to_string())?
+        .duration;
+
+    let library = tokio::task::spawn_blocking(move || sfx_folder.map(SfxLibrary::load_from_folder))
+        .await
+        .map_err(|e| e.to_string())?;
+    let categories = library.as_ref()
+        .map(|l| l.categories().clone())
+        .unwrap_or_else(|| SfxCategories::load(None));
+
+    sfx_cues::validate(&cues, duration, &categories, library.as_ref()).map_err(|e| e.to_string())?;
+    Ok(cues)
+}
+
+// 8f. save placed sfx (a finished job's sfx_events or a preview) as a cue sheet
+// .json gets json, anything else csv
+#[tauri::command]
+fn export_sfx_cues(path: String, placements: Vec<SfxPlacement>) -> Result<(), String> {
+    sfx_cues::save_cues(std::path::Path::new(&path), &sfx_cues::from_placements(&placements))
+        .map_err(|e| format!("couldnt write cue sheet: {}", e))
 }
 
 // ============================================
@@ -296,24 +679,48 @@ fn main() {
     log::info!("starting godz...");
 
     // create shared state
+    // capabilities start empty, the probe below fills them in
     let state = AppState {
         job_manager: Arc::new(Mutex::new(JobManager::new())),
+        previews: Arc::new(Mutex::new(JobManager::new())),
+        capabilities: Arc::new(RwLock::new(None)),
     };
 
     // build and run tauri app
     tauri::Builder::default()
         .manage(state)
+        .setup(|app| {
+            // the ffmpeg probe runs once, off the main thread - the hw test encodes take a second or two
+            // and the window shouldnt wait on them. jobs and processors all read the same result
+            let slot = Arc::clone(&app.state::<AppState>().capabilities);
+            if let Ok(mut probed) = slot.try_write_owned() {
+                tauri::async_runtime::spawn_blocking(move || {
+                    *probed = Some(Arc::new(Capabilities::probe()));
+                });
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             start_job,
+            render_preview,
+            render_layout_still,
             get_job_status,
             get_all_jobs,
             cancel_job,
+            clear_proxy_cache,
             check_dependencies,
             download_ytdlp,
+            download_whisper_model,
             validate_youtube_url,
             get_app_dirs,
             get_overlay_positions,
             get_output_formats,
+            scan_sfx_library,
+            set_sfx_assignment,
+            get_sfx_categories,
+            preview_sfx_placement,
+            validate_sfx_cues,
+            export_sfx_cues,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri app");
@@ -0,0 +1,505 @@
+// 1a. pip.rs - picture in picture styling
+// 1b. size, position, rounded corners, border and drop shadow for the broll box
+// 1c. builds the filter graph composite_pip runs
+
+use serde::{Deserialize, Serialize};
+use crate::filters::{is_filter_color, piecewise_linear_expr};
+use crate::jobs::OverlayPosition;
+use crate::processor::Dimensions;
+
+// 2a. drop shadow under the pip box
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PipShadow {
+    pub offset_x: i32,  // pixels, positive = right
+    pub offset_y: i32,  // pixels, positive = down
+    pub blur: u32,      // blur radius in pixels
+    pub opacity: f64,   // 0-1
+}
+
+impl Default for PipShadow {
+    fn default() -> Self {
+        Self {
+            offset_x: 8,
+            offset_y: 8,
+            blur: 12,
+            opacity: 0.5,
+        }
+    }
+}
+
+// 2b. how the box comes in or goes out
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PipTransitionKind {
+    Slide,  // slides in from / out to the nearest side edge
+    Scale,  // grows out of / shrinks into its anchor corner
+    Fade,   // alpha fade
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PipTransition {
+    pub kind: PipTransitionKind,
+    pub duration: f64,  // seconds
+}
+
+// 2c. move the box somewhere else at a given time
+// x/y use the same normalized space as PipStyle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PipKeyframe {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+// 2d. timing for the pip box
+// box only shows between show_from and show_until
+// keyframes glide the box to a new spot over move_duration seconds
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PipAnimation {
+    pub show_from: f64,
+    pub show_until: Option<f64>,  // None = until the video ends
+    pub entrance: Option<PipTransition>,
+    pub exit: Option<PipTransition>,
+    pub keyframes: Vec<PipKeyframe>,
+    pub move_duration: f64,
+}
+
+impl Default for PipAnimation {
+    fn default() -> Self {
+        Self {
+            show_from: 0.0,
+            show_until: None,
+            entrance: None,
+            exit: None,
+            keyframes: Vec::new(),
+            move_duration: 0.5,
+        }
+    }
+}
+
+impl PipAnimation {
+    // 2e. enable expression for the overlay filter
+    fn enable_expr(&self) -> String {
+        match self.show_until {
+            Some(until) => format!("between(t,{:.3},{:.3})", self.show_from, until),
+            None => format!("gte(t,{:.3})", self.show_from),
+        }
+    }
+
+    // 2f. normalized x/y over time from the keyframes
+    // holds each spot until the next keyframe, then glides over move_duration
+    fn path_exprs(&self, start_x: f64, start_y: f64) -> (String, String) {
+        let mut keyframes = self.keyframes.clone();
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let mut x_points = vec![(0.0, start_x)];
+        let mut y_points = vec![(0.0, start_y)];
+        let move_duration = self.move_duration.max(0.0);
+
+        for k in &keyframes {
+            let (prev_x, prev_y) = (x_points[x_points.len() - 1].1, y_points[y_points.len() - 1].1);
+            let (kx, ky) = (k.x.clamp(0.0, 1.0), k.y.clamp(0.0, 1.0));
+            // keyframes closer than a move apart just restart the glide from the held spot
+            let start = k.time.max(x_points[x_points.len() - 1].0);
+            x_points.push((start, prev_x));
+            y_points.push((start, prev_y));
+            x_points.push((start + move_duration, kx));
+            y_points.push((start + move_duration, ky));
+        }
+
+        (
+            format!("({})", piecewise_linear_expr(&x_points)),
+            format!("({})", piecewise_linear_expr(&y_points)),
+        )
+    }
+
+    // 2g. extra x offset for slide transitions
+    // slides toward whichever side edge the box started closest to
+    fn slide_offset_expr(&self, start_x: f64) -> Option<String> {
+        let dir = if start_x >= 0.5 { 1.0 } else { -1.0 };
+        let mut terms = Vec::new();
+
+        if let Some(t) = self.entrance.as_ref().filter(|t| t.kind == PipTransitionKind::Slide) {
+            terms.push(format!(
+                "{}*W*pow(1-clip((t-{:.3})/{:.3},0,1),2)",
+                dir, self.show_from, t.duration.max(0.01)
+            ));
+        }
+        if let (Some(t), Some(until)) = (self.exit.as_ref().filter(|t| t.kind == PipTransitionKind::Slide), self.show_until) {
+            let d = t.duration.max(0.01);
+            terms.push(format!(
+                "{}*W*pow(clip((t-{:.3})/{:.3},0,1),2)",
+                dir, until - d, d
+            ));
+        }
+
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join("+"))
+        }
+    }
+
+    // 2h. filters tacked onto the pip chain for scale and fade transitions
+    fn transition_filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        let entrance = self.entrance.as_ref();
+        let exit = self.exit.as_ref().filter(|_| self.show_until.is_some());
+        let until = self.show_until.unwrap_or(0.0);
+
+        // scale runs per frame so the box size can change over time
+        let mut scale_terms = Vec::new();
+        if let Some(t) = entrance.filter(|t| t.kind == PipTransitionKind::Scale) {
+            scale_terms.push(format!("clip((t-{:.3})/{:.3},0.01,1)", self.show_from, t.duration.max(0.01)));
+        }
+        if let Some(t) = exit.filter(|t| t.kind == PipTransitionKind::Scale) {
+            let d = t.duration.max(0.01);
+            scale_terms.push(format!("clip(({:.3}-t)/{:.3},0.01,1)", until, d));
+        }
+        if !scale_terms.is_empty() {
+            filters.push(format!(
+                "scale=w='max(2,trunc(iw*{}/2)*2)':h=-2:eval=frame",
+                scale_terms.join("*")
+            ));
+        }
+
+        // fades need alpha to work on, then the overlay blends them
+        let fade_in = entrance.filter(|t| t.kind == PipTransitionKind::Fade);
+        let fade_out = exit.filter(|t| t.kind == PipTransitionKind::Fade);
+        if fade_in.is_some() || fade_out.is_some() {
+            filters.push("format=yuva420p".to_string());
+        }
+        if let Some(t) = fade_in {
+            filters.push(format!("fade=t=in:st={:.3}:d={:.3}:alpha=1", self.show_from, t.duration));
+        }
+        if let Some(t) = fade_out {
+            filters.push(format!("fade=t=out:st={:.3}:d={:.3}:alpha=1", (until - t.duration).max(0.0), t.duration));
+        }
+
+        filters
+    }
+}
+
+// 2i. full style for the pip box
+// size is a fraction of the output, the broll keeps its own aspect inside that box
+// x/y are normalized inside the area left after margins (0,0 = top left, 1,1 = bottom right)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PipStyle {
+    pub size: f64,
+    pub x: f64,
+    pub y: f64,
+    pub margin: u32,
+    pub corner_radius: u32,
+    pub border_width: u32,
+    pub border_color: String,  // "white", "#ff00aa" or "#ff00aa80", see filters::is_filter_color
+    pub shadow: Option<PipShadow>,
+    pub animation: Option<PipAnimation>,
+}
+
+impl Default for PipStyle {
+    fn default() -> Self {
+        Self {
+            size: 0.3,
+            x: 0.0,
+            y: 0.0,
+            margin: 20,
+            corner_radius: 0,
+            border_width: 0,
+            border_color: "white".to_string(),
+            shadow: None,
+            animation: None,
+        }
+    }
+}
+
+impl PipStyle {
+    // 3a. style matching one of the corner presets
+    // this is what old jobs (position + pip_scale) map to
+    pub fn for_corner(position: OverlayPosition, pip_scale: f64) -> Self {
+        let (x, y) = match position {
+            OverlayPosition::TopRight => (1.0, 0.0),
+            OverlayPosition::BottomLeft => (0.0, 1.0),
+            OverlayPosition::BottomRight => (1.0, 1.0),
+            _ => (0.0, 0.0),  // top left + anything non-pip
+        };
+
+        Self {
+            size: pip_scale,
+            x,
+            y,
+            ..Self::default()
+        }
+    }
+
+    // 3b. clamp everything into ranges ffmpeg wont choke on
+    // pixel sizes are held to the box this style gets at target, so the box stays on screen
+    // and a corner radius never goes past half the box (the geq mask falls apart there)
+    pub fn sanitized(&self, target: Dimensions) -> Self {
+        let size = self.size.clamp(0.05, 1.0);
+        let (box_w, box_h) = box_size(size, target);
+        let box_min = box_w.min(box_h);
+
+        let border_width = self.border_width.min(box_min / 4);
+        let margin = self.margin
+            .min(target.width.saturating_sub(box_w + border_width * 2) / 2)
+            .min(target.height.saturating_sub(box_h + border_width * 2) / 2);
+
+        let border_color = if is_filter_color(&self.border_color, true) {
+            self.border_color.clone()
+        } else {
+            log::warn!("pip border color {:?} isnt a color, using white", self.border_color);
+            "white".to_string()
+        };
+
+        Self {
+            size,
+            x: self.x.clamp(0.0, 1.0),
+            y: self.y.clamp(0.0, 1.0),
+            margin,
+            corner_radius: self.corner_radius.min(box_min / 2),
+            border_width,
+            border_color,
+            shadow: self.shadow.as_ref().map(|s| PipShadow {
+                blur: s.blur.min(box_min / 4),
+                opacity: s.opacity.clamp(0.0, 1.0),
+                ..s.clone()
+            }),
+            ..self.clone()
+        }
+    }
+
+    // 3c. close off an open-ended animation at the video length
+    // exit transitions need a real end time to run against
+    pub fn fit_to_duration(&mut self, duration: f64) {
+        if let Some(animation) = self.animation.as_mut() {
+            if animation.show_until.is_none() && animation.exit.is_some() {
+                animation.show_until = Some(duration);
+            }
+        }
+    }
+
+    // 4a. x/y expressions for the overlay filter
+    // W/H are the main video, w/h the pip (border included)
+    // extra is padding around the image that shouldnt count (shadow bleed)
+    fn position_exprs(&self, extra: u32) -> (String, String) {
+        let (nx, ny) = match &self.animation {
+            Some(animation) => animation.path_exprs(self.x, self.y),
+            None => (format!("{:.4}", self.x), format!("{:.4}", self.y)),
+        };
+
+        let mut x = format!("{m}+(W-(w-{e})-2*{m})*{nx}", m = self.margin, e = extra, nx = nx);
+        let y = format!("{m}+(H-(h-{e})-2*{m})*{ny}", m = self.margin, e = extra, ny = ny);
+
+        if let Some(offset) = self.animation.as_ref().and_then(|a| a.slide_offset_expr(self.x)) {
+            x = format!("{}+{}", x, offset);
+        }
+
+        (x, y)
+    }
+
+    // 4b. overlay enable option, empty when the box is always on
+    fn enable_option(&self) -> String {
+        match &self.animation {
+            Some(animation) => format!(":enable='{}'", animation.enable_expr()),
+            None => String::new(),
+        }
+    }
+}
+
+// 5a. the filter chain that turns the raw broll into the styled pip box
+// input is whatever label you pass, output is [pip]
+// scale keeps aspect inside the size box, then border pad, then rounded alpha
+pub fn pip_chain(input: &str, style: &PipStyle, target: Dimensions) -> String {
+    let (box_w, box_h) = box_size(style.size, target);
+
+    let mut chain = format!(
+        "{}scale=w={}:h={}:force_original_aspect_ratio=decrease:force_divisible_by=2,setsar=1",
+        input, box_w, box_h
+    );
+
+    if style.border_width > 0 {
+        chain.push_str(&format!(
+            ",pad=iw+{b2}:ih+{b2}:{b}:{b}:color={c}",
+            b = style.border_width,
+            b2 = style.border_width * 2,
+            c = style.border_color
+        ));
+    }
+
+    if style.corner_radius > 0 {
+        chain.push_str(&format!(",format=yuva420p,{}", rounded_corners(style.corner_radius)));
+    }
+
+    if let Some(animation) = &style.animation {
+        for filter in animation.transition_filters() {
+            chain.push(',');
+            chain.push_str(&filter);
+        }
+    }
+
+    chain.push_str("[pip]");
+    chain
+}
+
+// the box the broll gets scaled into, before the border
+fn box_size(size: f64, target: Dimensions) -> (u32, u32) {
+    (
+        (target.width as f64 * size).round().max(2.0) as u32,
+        (target.height as f64 * size).round().max(2.0) as u32,
+    )
+}
+
+// 5b. geq alpha mask that cuts the corners round
+// pixels outside the corner circle go fully transparent
+fn rounded_corners(radius: u32) -> String {
+    let r = radius;
+    format!(
+        "geq=lum='p(X,Y)':cb='p(X,Y)':cr='p(X,Y)':\
+         a='if(gt(abs(W/2-X),W/2-{r})*gt(abs(H/2-Y),H/2-{r}),\
+         if(lte(hypot({r}-(W/2-abs(W/2-X)),{r}-(H/2-abs(H/2-Y))),{r}),255,0),255)'",
+        r = r
+    )
+}
+
+// 6a. full pip filter graph
+// [0:v] is the main (full screen) video, [1:v] is the pip source, result is [out]
+pub fn pip_graph(style: &PipStyle, target: Dimensions) -> String {
+    let style = style.sanitized(target);
+    let mut parts = vec![
+        format!("[0:v]scale={}:{},setsar=1[main]", target.width, target.height),
+        pip_chain("[1:v]", &style, target),
+    ];
+
+    let (x, y) = style.position_exprs(0);
+    let enable = style.enable_option();
+
+    match &style.shadow {
+        Some(shadow) => {
+            // shadow is a black, blurred copy of the pip's alpha
+            // padded first so the blur has room to spread
+            let bleed = shadow.blur * 2;
+            parts.push("[pip]split[pipfg][pipbg]".to_string());
+
+            let mut shadow_chain = format!(
+                "[pipbg]format=rgba,colorchannelmixer=rr=0:gg=0:bb=0:aa={:.3},\
+                 pad=iw+{}:ih+{}:{}:{}:color=black@0.0",
+                shadow.opacity, bleed * 2, bleed * 2, bleed, bleed
+            );
+            if shadow.blur > 0 {
+                shadow_chain.push_str(&format!(
+                    ",format=yuva420p,boxblur=luma_radius={b}:chroma_radius={b}:alpha_radius={b}",
+                    b = shadow.blur
+                ));
+            }
+            shadow_chain.push_str("[shadow]");
+            parts.push(shadow_chain);
+
+            let (sx, sy) = style.position_exprs(bleed * 2);
+            parts.push(format!(
+                "[main][shadow]overlay=x='{}+({})':y='{}+({})'{}[shadowed]",
+                sx, shadow.offset_x as i64 - bleed as i64,
+                sy, shadow.offset_y as i64 - bleed as i64,
+                enable
+            ));
+            parts.push(format!("[shadowed][pipfg]overlay=x='{}':y='{}'{}[out]", x, y, enable));
+        }
+        None => {
+            parts.push(format!("[main][pip]overlay=x='{}':y='{}'{}[out]", x, y, enable));
+        }
+    }
+
+    parts.join(";")
+}
+
+// 7a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corner_presets() {
+        let style = PipStyle::for_corner(OverlayPosition::BottomRight, 0.25);
+        assert_eq!((style.x, style.y, style.size), (1.0, 1.0, 0.25));
+
+        let style = PipStyle::for_corner(OverlayPosition::TopLeft, 0.3);
+        assert_eq!((style.x, style.y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_plain_pip_graph_keeps_aspect() {
+        let graph = pip_graph(&PipStyle::default(), Dimensions::youtube());
+        assert!(graph.contains("scale=w=576:h=324:force_original_aspect_ratio=decrease"));
+        assert!(graph.ends_with("[main][pip]overlay=x='20+(W-(w-0)-2*20)*0.0000':y='20+(H-(h-0)-2*20)*0.0000'[out]"));
+    }
+
+    #[test]
+    fn test_styled_pip_graph() {
+        let style = PipStyle {
+            corner_radius: 24,
+            border_width: 4,
+            shadow: Some(PipShadow::default()),
+            ..PipStyle::default()
+        };
+        let graph = pip_graph(&style, Dimensions::tiktok());
+        assert!(graph.contains("pad=iw+8:ih+8:4:4:color=white"));
+        assert!(graph.contains("geq="));
+        assert!(graph.contains("[shadowed][pipfg]overlay"));
+    }
+
+    #[test]
+    fn test_hostile_border_color_never_reaches_the_graph() {
+        let style = PipStyle {
+            border_width: 4,
+            border_color: "red:x=0,drawtext=text='hi'[pwn];[pwn]".to_string(),
+            ..PipStyle::default()
+        };
+        let graph = pip_graph(&style, Dimensions::youtube());
+        assert!(graph.contains("pad=iw+8:ih+8:4:4:color=white"));
+        assert!(!graph.contains("pwn"));
+
+        let style = PipStyle { border_width: 4, border_color: "#ff00aa80".to_string(), ..PipStyle::default() };
+        assert!(pip_graph(&style, Dimensions::youtube()).contains("color=#ff00aa80"));
+    }
+
+    #[test]
+    fn test_sizes_clamped_to_the_box() {
+        // 0.3 of 1920x1080 is a 576x324 box
+        let style = PipStyle {
+            margin: 5000,
+            corner_radius: 1000,
+            border_width: 500,
+            shadow: Some(PipShadow { blur: 900, ..PipShadow::default() }),
+            ..PipStyle::default()
+        };
+        let clean = style.sanitized(Dimensions::youtube());
+        assert_eq!(clean.border_width, 81);
+        assert_eq!(clean.corner_radius, 162);
+        assert_eq!(clean.shadow.unwrap().blur, 81);
+        // (1080 - 324 - 162) / 2 - the box still fits between the margins
+        assert_eq!(clean.margin, 297);
+
+        // sane values come through untouched
+        let style = PipStyle { corner_radius: 24, border_width: 4, ..PipStyle::default() };
+        assert_eq!(style.sanitized(Dimensions::youtube()), style);
+    }
+
+    #[test]
+    fn test_animated_pip_graph() {
+        let mut style = PipStyle {
+            animation: Some(PipAnimation {
+                show_from: 2.0,
+                entrance: Some(PipTransition { kind: PipTransitionKind::Slide, duration: 0.4 }),
+                exit: Some(PipTransition { kind: PipTransitionKind::Fade, duration: 0.5 }),
+                keyframes: vec![PipKeyframe { time: 5.0, x: 1.0, y: 1.0 }],
+                ..PipAnimation::default()
+            }),
+            ..PipStyle::default()
+        };
+        style.fit_to_duration(12.0);
+
+        let graph = pip_graph(&style, Dimensions::youtube());
+        assert!(graph.contains("fade=t=out:st=11.500:d=0.500:alpha=1"));
+        assert!(graph.contains(":enable='between(t,2.000,12.000)'[out]"));
+        assert!(graph.contains("-1*W*pow(1-clip((t-2.000)/0.400,0,1),2)"));
+        assert!(graph.contains("gte(t,5.000)*lt(t,5.500)"));
+    }
+}
@@ -0,0 +1,272 @@
+// 1a. presets.rs - platform export presets
+// 1b. each OutputFormat carries the platform's rules, not just a size
+// max length, max upload size, fps, bitrate, audio rate
+// 1c. jobs get checked against these before anything renders
+// numbers are the platforms' published upload specs, they change every so often
+
+use serde::Serialize;
+use crate::jobs::OutputFormat;
+use crate::processor::Dimensions;
+use crate::encoder::{EncoderProfile, VideoCodec};
+use crate::utils;
+
+// 2a. one preset, also what the format dropdown gets
+// value is what start_job takes as output_format
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportPreset {
+    pub value: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub fps: Option<f64>,                  // None = keep the source rate
+    pub max_duration: Option<f64>,         // seconds
+    pub max_file_size_mb: Option<u64>,
+    pub video_bitrate_kbps: Option<u32>,   // None = constant quality
+    pub audio_bitrate_kbps: u32,
+    pub audio_sample_rate: u32,
+    pub loudness_lufs: f64,                // what the platform normalizes playback to, the mastering target
+    pub codecs: &'static [VideoCodec],     // what the platform takes, first = preferred
+}
+
+const MP4_CODECS: &[VideoCodec] = &[VideoCodec::H264, VideoCodec::Hevc];
+const ANY_CODEC: &[VideoCodec] = &[VideoCodec::H264, VideoCodec::Hevc, VideoCodec::Av1, VideoCodec::Vp9];
+
+impl ExportPreset {
+    // 3a. the preset behind a format
+    pub fn for_format(format: &OutputFormat) -> Self {
+        match format {
+            OutputFormat::YouTube => Self {
+                value: "youtube",
+                label: "YouTube",
+                description: "16:9 landscape for YouTube",
+                width: 1920,
+                height: 1080,
+                fps: Some(30.0),
+                max_duration: Some(12.0 * 3600.0),
+                max_file_size_mb: Some(256 * 1024),
+                video_bitrate_kbps: Some(8000),
+                audio_bitrate_kbps: 384,
+                audio_sample_rate: 48000,
+                loudness_lufs: -14.0,
+                codecs: ANY_CODEC,
+            },
+            OutputFormat::YouTubeShorts => Self {
+                value: "youtube-shorts",
+                label: "YouTube Shorts",
+                description: "9:16 portrait, up to 3 minutes",
+                width: 1080,
+                height: 1920,
+                fps: Some(30.0),
+                max_duration: Some(180.0),
+                max_file_size_mb: None,
+                video_bitrate_kbps: Some(8000),
+                audio_bitrate_kbps: 192,
+                audio_sample_rate: 48000,
+                loudness_lufs: -14.0,
+                codecs: ANY_CODEC,
+            },
+            OutputFormat::TikTok => Self {
+                value: "tiktok",
+                label: "TikTok",
+                description: "9:16 portrait for TikTok",
+                width: 1080,
+                height: 1920,
+                fps: Some(30.0),
+                max_duration: Some(600.0),
+                max_file_size_mb: Some(287),  // the mobile app limit, web takes more
+                video_bitrate_kbps: Some(6000),
+                audio_bitrate_kbps: 128,
+                audio_sample_rate: 44100,
+                loudness_lufs: -14.0,
+                codecs: MP4_CODECS,
+            },
+            OutputFormat::InstagramReels => Self {
+                value: "instagram-reels",
+                label: "Instagram Reels",
+                description: "9:16 portrait, up to 3 minutes",
+                width: 1080,
+                height: 1920,
+                fps: Some(30.0),
+                max_duration: Some(180.0),
+                max_file_size_mb: Some(4096),
+                video_bitrate_kbps: Some(5000),
+                audio_bitrate_kbps: 128,
+                audio_sample_rate: 48000,
+                loudness_lufs: -14.0,
+                codecs: MP4_CODECS,
+            },
+            OutputFormat::InstagramFeed => Self {
+                value: "instagram",
+                label: "Instagram Feed",
+                description: "4:5 for the Instagram feed",
+                width: 1080,
+                height: 1350,
+                fps: Some(30.0),
+                max_duration: Some(3600.0),
+                max_file_size_mb: Some(4096),
+                video_bitrate_kbps: Some(5000),
+                audio_bitrate_kbps: 128,
+                audio_sample_rate: 48000,
+                loudness_lufs: -14.0,
+                codecs: MP4_CODECS,
+            },
+            OutputFormat::X => Self {
+                value: "x",
+                label: "X",
+                description: "16:9 landscape, 2:20 max without premium",
+                width: 1920,
+                height: 1080,
+                fps: Some(30.0),
+                max_duration: Some(140.0),
+                max_file_size_mb: Some(512),
+                video_bitrate_kbps: Some(5000),
+                audio_bitrate_kbps: 128,
+                audio_sample_rate: 44100,
+                loudness_lufs: -14.0,
+                codecs: &[VideoCodec::H264],
+            },
+            OutputFormat::Custom => Self {
+                value: "custom",
+                label: "Custom",
+                description: "Pick your own dimensions",
+                width: 0,
+                height: 0,
+                fps: None,
+                max_duration: None,
+                max_file_size_mb: None,
+                video_bitrate_kbps: None,
+                audio_bitrate_kbps: 192,
+                audio_sample_rate: 48000,
+                loudness_lufs: -16.0,
+                codecs: ANY_CODEC,
+            },
+        }
+    }
+
+    // 3b. every preset in dropdown order
+    pub fn all() -> Vec<Self> {
+        OutputFormat::all().iter().map(Self::for_format).collect()
+    }
+
+    // 4a. encoder settings picked for this platform
+    // used when the job doesnt bring its own profile
+    pub fn encoder_profile(&self) -> EncoderProfile {
+        EncoderProfile {
+            codec: self.codecs[0],
+            bitrate_kbps: self.video_bitrate_kbps,
+            keyframe_interval: self.fps.map(|fps| (fps * 2.0).round() as u32),
+            frame_rate: self.fps,
+            audio_bitrate_kbps: self.audio_bitrate_kbps,
+            audio_sample_rate: Some(self.audio_sample_rate),
+            ..EncoderProfile::default()
+        }
+    }
+
+    // 5a. check a job before rendering
+    // errors mean ffmpeg would fail or the platform would flat out reject the codec,
+    // warnings are things the user should know but might want anyway (too long, too big)
+    pub fn check(&self, duration: f64, target: Dimensions, encoder: &EncoderProfile) -> PresetCheck {
+        let mut check = PresetCheck::default();
+
+        if target.width == 0 || target.height == 0 {
+            check.errors.push("output size cant be zero".to_string());
+        } else if target.width % 2 == 1 || target.height % 2 == 1 {
+            check.errors.push(format!(
+                "output size {}x{} has to be even on both sides for yuv420p",
+                target.width, target.height
+            ));
+        }
+
+        if !self.codecs.contains(&encoder.codec) {
+            check.errors.push(format!("{} doesnt take {:?} uploads", self.label, encoder.codec));
+        }
+
+        if let Some(max) = self.max_duration.filter(|max| duration > *max) {
+            check.warnings.push(format!(
+                "your video is {} but {} only takes up to {}, it'll get cut off or rejected",
+                utils::format_duration(duration),
+                self.label,
+                utils::format_duration(max)
+            ));
+        }
+
+        // fit to size lands on the target, so only the target itself needs checking
+        if let (Some(max_mb), Some(target_mb)) = (self.max_file_size_mb, encoder.target_size_mb) {
+            if target_mb > max_mb as f64 {
+                check.warnings.push(format!(
+                    "fitting to {:.0} MB is over {}'s {} MB limit",
+                    target_mb, self.label, max_mb
+                ));
+            }
+        }
+
+        // otherwise only predictable with a target bitrate, crf sizes depend on the footage
+        let bitrate = encoder.bitrate_kbps.filter(|_| encoder.target_size_mb.is_none());
+        if let (Some(max_mb), Some(kbps)) = (self.max_file_size_mb, bitrate) {
+            let estimate_mb = (kbps + encoder.audio_bitrate_kbps) as f64 * duration / 8.0 / 1024.0;
+            if estimate_mb > max_mb as f64 {
+                check.warnings.push(format!(
+                    "at {}kbps this comes out around {:.0} MB, over {}'s {} MB limit",
+                    kbps, estimate_mb, self.label, max_mb
+                ));
+            }
+        }
+
+        if let (Some(want), Some(fps)) = (self.fps, encoder.frame_rate) {
+            if fps > want * 2.0 {
+                check.warnings.push(format!("{} plays back at {}fps, {}fps is wasted bitrate", self.label, want, fps));
+            }
+        }
+
+        check
+    }
+}
+
+// 5b. result of a preset check
+#[derive(Debug, Clone, Default)]
+pub struct PresetCheck {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+// 6a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_pick_encoder_settings() {
+        let tiktok = ExportPreset::for_format(&OutputFormat::TikTok).encoder_profile();
+        assert_eq!(tiktok.bitrate_kbps, Some(6000));
+        assert_eq!(tiktok.keyframe_interval, Some(60));
+        assert_eq!((tiktok.audio_bitrate_kbps, tiktok.audio_sample_rate), (128, Some(44100)));
+
+        let custom = ExportPreset::for_format(&OutputFormat::Custom).encoder_profile();
+        assert_eq!((custom.bitrate_kbps, custom.frame_rate), (None, None));
+        assert_eq!(ExportPreset::all().len(), OutputFormat::all().len());
+    }
+
+    #[test]
+    fn test_check_warns_and_errors() {
+        let shorts = ExportPreset::for_format(&OutputFormat::YouTubeShorts);
+        let ok = shorts.check(45.0, OutputFormat::YouTubeShorts.dimensions(), &shorts.encoder_profile());
+        assert!(ok.errors.is_empty() && ok.warnings.is_empty());
+
+        let long = shorts.check(240.0, OutputFormat::YouTubeShorts.dimensions(), &shorts.encoder_profile());
+        assert!(long.warnings[0].contains("4:00") && long.warnings[0].contains("3:00"));
+
+        // 2 min at 40mbps is ~590MB, over X's 512
+        let x = ExportPreset::for_format(&OutputFormat::X);
+        let heavy = EncoderProfile { bitrate_kbps: Some(40000), codec: VideoCodec::Av1, ..x.encoder_profile() };
+        let bad = x.check(120.0, Dimensions { width: 1279, height: 720 }, &heavy);
+        assert_eq!(bad.errors.len(), 2);
+        assert_eq!(bad.warnings.len(), 1);
+
+        // a size target replaces the bitrate estimate
+        let fitted = EncoderProfile { target_size_mb: Some(600.0), ..heavy };
+        let over = x.check(120.0, OutputFormat::X.dimensions(), &fitted);
+        assert_eq!(over.warnings.len(), 1);
+        assert!(over.warnings[0].contains("600 MB"));
+    }
+}
@@ -4,9 +4,19 @@
 // those ffmpeg filter chains are wild
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::process::Command;
 use thiserror::Error;
-use crate::jobs::OverlayPosition;
+use crate::pip::{self, PipStyle};
+use crate::layers::CompositeLayers;
+use crate::reframe::CropPath;
+use crate::bumpers::{self, BumperTransition, JoinSegment};
+use crate::audio::MusicBed;
+use crate::sfx::{self, SfxPlacement};
+use crate::encoder::EncoderProfile;
+use crate::capabilities::Capabilities;
+use crate::hwaccel;
 
 // 2a. errors that can happen during processing
 // using thiserror cuz writing error boilerplate sucks
@@ -59,6 +69,7 @@ pub struct VideoMetadata {
     pub width: u32,
     pub height: u32,
     pub fps: f64,
+    pub has_audio: bool,
 }
 
 // 3c. gpu encoder types we can use
@@ -71,23 +82,110 @@ pub enum GpuEncoder {
     None,       // fallback to cpu (libx264)
 }
 
+// 3d. the three layouts composite_* and render_still draw
+// input 0 / input 1 are the two videos in the order the caller passes them
+#[derive(Debug, Clone, Copy)]
+pub enum Layout<'a> {
+    SplitScreen { ratio: f64, crops: [Option<&'a CropPath>; 2] },  // input 0 on top, ratio = its share
+    Pip(&'a PipStyle),                                            // input 0 full screen, input 1 in the box
+    SideBySide { ratio: f64, crops: [Option<&'a CropPath>; 2] },   // input 0 on the left
+}
+
+impl Layout<'_> {
+    // the filter graph for this layout, result is [out]
+    pub fn graph(&self, target: Dimensions) -> String {
+        match *self {
+            Layout::SplitScreen { ratio, crops } => {
+                // calculate heights for each section
+                let top_height = (target.height as f64 * ratio) as u32;
+                let bottom_height = target.height - top_height;
+
+                // scale and stack, [0] is top video, [1] is bottom video
+                format!(
+                    "[0:v]{}scale={}:{},setsar=1[top];\
+                     [1:v]{}scale={}:{},setsar=1[bottom];\
+                     [top][bottom]vstack=inputs=2[out]",
+                    crop_prefix(crops[0], target.width, top_height), target.width, top_height,
+                    crop_prefix(crops[1], target.width, bottom_height), target.width, bottom_height
+                )
+            }
+            // all the sizing/border/shadow logic lives in pip.rs
+            Layout::Pip(style) => pip::pip_graph(style, target),
+            Layout::SideBySide { ratio, crops } => {
+                let left_width = (target.width as f64 * ratio) as u32;
+                let right_width = target.width - left_width;
+
+                // scale both videos and stack horizontally
+                format!(
+                    "[0:v]{}scale={}:{},setsar=1[left];\
+                     [1:v]{}scale={}:{},setsar=1[right];\
+                     [left][right]hstack=inputs=2[out]",
+                    crop_prefix(crops[0], left_width, target.height), left_width, target.height,
+                    crop_prefix(crops[1], right_width, target.height), right_width, target.height
+                )
+            }
+        }
+    }
+
+    // where the title bar sits, only split screen has a seam to put it on
+    fn split_line(&self) -> Option<f64> {
+        match self {
+            Layout::SplitScreen { ratio, .. } => Some(*ratio),
+            _ => None,
+        }
+    }
+}
+
 // 4a. the main processor struct
+// cloning skips the gpu probe, handy for one render per output format
+// clones share hw_failed, so one broken hw encode moves the whole job to the cpu
+#[derive(Clone)]
 pub struct Processor {
     temp_dir: PathBuf,
     gpu_encoder: GpuEncoder,
+    vaapi_device: Option<PathBuf>,
+    hw_failed: Arc<AtomicBool>,
+    encoder: EncoderProfile,  // codec/quality the job picked
+    layers: CompositeLayers,  // captions etc drawn over every composite
+    voice_filter: Option<String>,  // cleanup on the user audio, applied in the composite
 }
 
 impl Processor {
-    pub fn new(temp_dir: impl AsRef<Path>) -> ProcessResult<Self> {
+    pub fn new(temp_dir: impl AsRef<Path>, capabilities: &Capabilities) -> ProcessResult<Self> {
         let temp_dir = temp_dir.as_ref().to_path_buf();
         std::fs::create_dir_all(&temp_dir)?;
         
-        // detect what gpu encoder we can use
-        // this runs once when processor is created
-        let gpu_encoder = detect_gpu_encoder();
-        log::info!("using encoder: {:?}", gpu_encoder);
+        // gpu encoder comes from the shared probe, no ffmpeg spawned per processor
+        let hw = capabilities.hw_encoder.clone();
         
-        Ok(Self { temp_dir, gpu_encoder })
+        Ok(Self {
+            temp_dir,
+            gpu_encoder: hw.kind,
+            vaapi_device: hw.vaapi_device,
+            hw_failed: Arc::new(AtomicBool::new(false)),
+            encoder: EncoderProfile::default(),
+            layers: CompositeLayers::default(),
+            voice_filter: None,
+        })
+    }
+
+    // 4b. set the layers the composite_* functions stack on top
+    pub fn with_layers(mut self, layers: CompositeLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    // set the cleanup chain for the talking head audio (highpass, denoise)
+    // it goes on before sfx/music are mixed in, so only the voice gets it
+    pub fn with_voice_filter(mut self, filter: Option<String>) -> Self {
+        self.voice_filter = filter;
+        self
+    }
+
+    // 4c. set the encoder profile every encoding pass uses
+    pub fn with_encoder(mut self, encoder: EncoderProfile) -> Self {
+        self.encoder = encoder;
+        self
     }
 
     // 5a. check if ffmpeg is available
@@ -95,33 +193,98 @@ impl Processor {
         which::which("ffmpeg").is_ok() && which::which("ffprobe").is_ok()
     }
 
-    // 5b. get encoding args based on gpu availability
-    // gpu encoding is way faster when available
-    fn get_encoder_args(&self) -> Vec<String> {
-        match self.gpu_encoder {
-            GpuEncoder::Nvenc => vec![
-                "-c:v".to_string(), "h264_nvenc".to_string(),
-                "-preset".to_string(), "p4".to_string(),  // balanced preset
-                "-rc".to_string(), "vbr".to_string(),
-                "-cq".to_string(), "23".to_string(),
-            ],
-            GpuEncoder::Vaapi => vec![
-                "-vaapi_device".to_string(), "/dev/dri/renderD128".to_string(),
-                "-c:v".to_string(), "h264_vaapi".to_string(),
-                "-qp".to_string(), "23".to_string(),
-            ],
-            GpuEncoder::Videotoolbox => vec![
-                "-c:v".to_string(), "h264_videotoolbox".to_string(),
-                "-q:v".to_string(), "65".to_string(),
-            ],
-            GpuEncoder::None => vec![
-                "-c:v".to_string(), "libx264".to_string(),
-                "-preset".to_string(), "fast".to_string(),
-                "-crf".to_string(), "23".to_string(),
-            ],
+    // 5b. the gpu encoder to use right now
+    // gpu encoding is way faster when available, once it failed this job stays on the cpu
+    fn gpu(&self) -> GpuEncoder {
+        if self.hw_failed.load(Ordering::Relaxed) { GpuEncoder::None } else { self.gpu_encoder }
+    }
+
+    // ffmpeg encoder the renders are going through, ends up in the job status
+    pub fn encoder_used(&self) -> &'static str {
+        self.encoder.encoder_name(self.gpu())
+    }
+
+    // true once a hw encode failed and got redone on the cpu
+    pub fn fell_back_to_cpu(&self) -> bool {
+        self.hw_failed.load(Ordering::Relaxed)
+    }
+
+    // 5c. run one encode - callers pass inputs/filters/maps and the audio handling,
+    // encoder + output go on here. a failed hw encode gets one retry on the cpu
+    async fn encode(&self, input_args: Vec<String>, audio_args: Vec<String>, output_path: &Path, what: &str) -> ProcessResult<()> {
+        let gpu = self.gpu();
+        match self.encode_with(gpu, input_args.clone(), audio_args.clone(), output_path, what).await {
+            Err(e) if self.encoder.backend(gpu) != GpuEncoder::None => {
+                log::warn!("{} failed on {}, redoing it on the cpu", what, self.encoder.encoder_name(gpu));
+                log::debug!("hw encode error: {}", e);
+                self.hw_failed.store(true, Ordering::Relaxed);
+                self.encode_with(GpuEncoder::None, input_args, audio_args, output_path, what).await
+            }
+            result => result,
         }
     }
 
+    // 5d. one encode on a given backend
+    // target size profiles go through twice, pass 1 only writes the rate stats
+    async fn encode_with(
+        &self,
+        gpu: GpuEncoder,
+        input_args: Vec<String>,
+        audio_args: Vec<String>,
+        output_path: &Path,
+        what: &str,
+    ) -> ProcessResult<()> {
+        let two_pass = self.encoder.two_pass();
+        let hw_args = |args: Vec<String>| match (self.encoder.backend(gpu), &self.vaapi_device) {
+            (GpuEncoder::Vaapi, Some(device)) => hwaccel::upload_args(args, device),
+            _ => args,
+        };
+        let stem = output_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let passlog = self.temp_dir.join(format!("{}_2pass", stem));
+
+        if two_pass {
+            let mut args = input_args.clone();
+            args.extend(self.encoder.video_args(gpu));
+            args.extend(self.encoder.pass_args(1, &passlog));
+            args.extend(["-an".to_string(), "-f".to_string(), "null".to_string(), "-".to_string()]);
+
+            let output = Command::new("ffmpeg").args(hw_args(args)).output().await?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                log::error!("{} (pass 1) failed: {}", what, stderr);
+                return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+            }
+        }
+
+        let mut args = input_args;
+        args.extend(self.encoder.video_args(gpu));
+        if two_pass {
+            args.extend(self.encoder.pass_args(2, &passlog));
+        }
+        args.extend(audio_args);
+        args.push(output_path.to_str().unwrap().to_string());
+
+        let output = Command::new("ffmpeg").args(hw_args(args)).output().await?;
+
+        // x264/x265 leave a few stats files named after the log prefix
+        if two_pass {
+            let prefix = passlog.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if let Ok(entries) = std::fs::read_dir(&self.temp_dir) {
+                for entry in entries.flatten().filter(|e| e.file_name().to_string_lossy().starts_with(&prefix)) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("{} failed: {}", what, stderr);
+            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+        }
+
+        Ok(())
+    }
+
     // 6a. get video metadata using ffprobe
     // we need duration to know how many clips to cut
     pub async fn get_metadata(&self, video_path: &Path) -> ProcessResult<VideoMetadata> {
@@ -169,9 +332,106 @@ impl Processor {
             width: video_stream["width"].as_u64().unwrap_or(1920) as u32,
             height: video_stream["height"].as_u64().unwrap_or(1080) as u32,
             fps,
+            has_audio: streams.iter().any(|s| s["codec_type"] == "audio"),
         })
     }
 
+    // 6b. grab downscaled rgb frames for analysis
+    // ffmpeg pipes rawvideo to stdout so nothing touches disk
+    // each returned frame is width * height * 3 bytes
+    pub async fn sample_frames(
+        &self,
+        video_path: &Path,
+        sample_fps: f64,
+        width: u32,
+        height: u32,
+    ) -> ProcessResult<Vec<Vec<u8>>> {
+        let filter = format!("fps={},scale={}:{}", sample_fps, width, height);
+
+        let output = Command::new("ffmpeg")
+            .args([
+                "-v", "error",
+                "-i", video_path.to_str().unwrap(),
+                "-vf", &filter,
+                "-an",
+                "-f", "rawvideo",
+                "-pix_fmt", "rgb24",
+                "-",
+            ])
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("frame sampling failed: {}", stderr);
+            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+        }
+
+        let frame_size = (width * height * 3) as usize;
+        Ok(output.stdout
+            .chunks_exact(frame_size)
+            .map(|frame| frame.to_vec())
+            .collect())
+    }
+
+    // 6c. pull the audio out as mono 16-bit wav
+    // speech to text engines want exactly this
+    pub async fn extract_audio(
+        &self,
+        video_path: &Path,
+        output_path: &Path,
+        sample_rate: u32,
+    ) -> ProcessResult<PathBuf> {
+        let output = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-i", video_path.to_str().unwrap(),
+                "-vn",
+                "-ac", "1",
+                "-ar", &sample_rate.to_string(),
+                "-c:a", "pcm_s16le",
+                output_path.to_str().unwrap(),
+            ])
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("audio extraction failed: {}", stderr);
+            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+        }
+
+        Ok(output_path.to_path_buf())
+    }
+
+    // 6d. decode any audio file to interleaved stereo f32 samples
+    // for mixing in rust, piped through stdout like 6b
+    pub async fn decode_audio(&self, path: &Path, sample_rate: u32) -> ProcessResult<Vec<f32>> {
+        let output = Command::new("ffmpeg")
+            .args([
+                "-v", "error",
+                "-i", path.to_str().unwrap(),
+                "-vn",
+                "-ac", "2",
+                "-ar", &sample_rate.to_string(),
+                "-f", "f32le",
+                "-",
+            ])
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("audio decode failed: {}", stderr);
+            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+        }
+
+        Ok(output.stdout
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
     // 7a. cut a clip from a video
     // start_time and duration in seconds
     // mute_audio is crucial for avoiding content id
@@ -183,34 +443,20 @@ impl Processor {
         duration: f64,
         mute_audio: bool,
     ) -> ProcessResult<PathBuf> {
-        let mut args = vec![
+        let args = vec![
             "-y".to_string(),  // overwrite output
             "-ss".to_string(), format!("{:.3}", start_time),  // seek before -i is faster
             "-i".to_string(), input_path.to_str().unwrap().to_string(),
             "-t".to_string(), format!("{:.3}", duration),
         ];
 
-        // add encoder args
-        args.extend(self.get_encoder_args());
-
-        if mute_audio {
-            args.push("-an".to_string());  // strip audio completely
+        let audio = if mute_audio {
+            vec!["-an".to_string()]  // strip audio completely
         } else {
-            args.extend(["-c:a".to_string(), "aac".to_string()]);
-        }
-
-        args.push(output_path.to_str().unwrap().to_string());
-
-        let output = Command::new("ffmpeg")
-            .args(&args)
-            .output()
-            .await?;
+            self.encoder.audio_args()
+        };
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            log::error!("ffmpeg cut failed: {}", stderr);
-            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
-        }
+        self.encode(args, audio, output_path, "ffmpeg cut").await?;
 
         Ok(output_path.to_path_buf())
     }
@@ -224,38 +470,130 @@ impl Processor {
         target: Dimensions,
     ) -> ProcessResult<PathBuf> {
         // scale filter that keeps aspect ratio and adds padding
-        let filter = format!(
-            "scale={}:{}:force_original_aspect_ratio=decrease,pad={}:{}:(ow-iw)/2:(oh-ih)/2:black",
-            target.width, target.height, target.width, target.height
-        );
+        let filter = fit_filter(target);
 
-        let mut args = vec![
+        let args = vec![
             "-y".to_string(),
             "-i".to_string(), input_path.to_str().unwrap().to_string(),
             "-vf".to_string(), filter,
         ];
         
-        args.extend(self.get_encoder_args());
-        args.extend([
-            "-c:a".to_string(), "copy".to_string(),
-            output_path.to_str().unwrap().to_string(),
-        ]);
+        self.encode(args, vec!["-c:a".to_string(), "copy".to_string()], output_path, "scale").await?;
 
-        let output = Command::new("ffmpeg")
-            .args(&args)
-            .output()
-            .await?;
+        Ok(output_path.to_path_buf())
+    }
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+    // 7c. conform a clip so it can be joined onto a render
+    // same fit as scale_video plus a fixed fps, square pixels and 48k stereo audio
+    // gain_db evens out the level, clips without audio get silence so concat has a track
+    pub async fn conform_video(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        target: Dimensions,
+        fps: f64,
+        gain_db: f64,
+    ) -> ProcessResult<PathBuf> {
+        let metadata = self.get_metadata(input_path).await?;
+        let filter = format!("{},fps={:.3},setsar=1,format=yuv420p", fit_filter(target), fps);
+
+        let mut args = vec![
+            "-y".to_string(),
+            "-i".to_string(), input_path.to_str().unwrap().to_string(),
+        ];
+
+        if metadata.has_audio {
+            args.extend([
+                "-af".to_string(),
+                format!("aresample=48000,aformat=channel_layouts=stereo,volume={:.2}dB", gain_db),
+            ]);
+        } else {
+            args.extend([
+                "-f".to_string(), "lavfi".to_string(),
+                "-i".to_string(), "anullsrc=r=48000:cl=stereo".to_string(),
+                "-map".to_string(), "0:v".to_string(),
+                "-map".to_string(), "1:a".to_string(),
+                "-shortest".to_string(),
+            ]);
         }
 
+        args.extend(["-vf".to_string(), filter]);
+        self.encode(args, self.encoder.audio_args(), output_path, "conform").await?;
+
+        Ok(output_path.to_path_buf())
+    }
+
+    // 7d. mezzanine copy of a broll source for the proxy cache
+    // scaled down to just cover the target with the aspect kept, so the fit/crop later works the same
+    // never scaled up - a proxy bigger than its source is slower to cut than the source
+    // no audio, broll clips get cut muted anyway. keyframes + fps come from the encoder profile
+    pub async fn make_proxy(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        target: Dimensions,
+    ) -> ProcessResult<PathBuf> {
+        let filter = format!("{},setsar=1,format=yuv420p", proxy_scale(target));
+
+        let args = vec![
+            "-y".to_string(),
+            "-i".to_string(), input_path.to_str().unwrap().to_string(),
+            "-vf".to_string(), filter,
+        ];
+
+        self.encode(args, vec!["-an".to_string()], output_path, "proxy").await?;
+
         Ok(output_path.to_path_buf())
     }
 
+    // 7e. frame accurate version of cut_clip - in/out are whole frames of the source
+    // the -ss before -i lands a bit early on a keyframe, the second -ss after it decodes
+    // forward to the exact frame. -frames:v instead of -t so nothing gets rounded
+    // returns the clip's real duration from ffprobe, not the one we asked for
+    pub async fn cut_clip_frames(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        start_frame: u64,
+        frames: u64,
+        fps: f64,
+        mute_audio: bool,
+    ) -> ProcessResult<f64> {
+        const PRE_ROLL: f64 = 2.0;
+        let start = start_frame as f64 / fps;
+        let coarse = (start - PRE_ROLL).max(0.0);
+        let duration = frames as f64 / fps;
+
+        let args = vec![
+            "-y".to_string(),
+            "-ss".to_string(), format!("{:.6}", coarse),
+            "-i".to_string(), input_path.to_str().unwrap().to_string(),
+            "-ss".to_string(), format!("{:.6}", start - coarse),
+            "-frames:v".to_string(), frames.to_string(),
+        ];
+
+        let audio = if mute_audio {
+            vec!["-an".to_string()]
+        } else {
+            let mut audio = self.encoder.audio_args();
+            audio.extend(["-t".to_string(), format!("{:.6}", duration)]);
+            audio
+        };
+
+        self.encode(args, audio, output_path, "ffmpeg frame cut").await?;
+
+        match self.get_metadata(output_path).await {
+            Ok(metadata) if metadata.duration > 0.0 => Ok(metadata.duration),
+            _ => {
+                log::warn!("couldnt probe {}, assuming {:.3}s", output_path.display(), duration);
+                Ok(duration)
+            }
+        }
+    }
+
     // 8a. composite split screen - broll top, user bottom (or vice versa)
     // split_ratio determines how much screen broll takes
+    // crops are optional reframe paths, indexed like the inputs
     pub async fn composite_split_screen(
         &self,
         top_video: &Path,
@@ -263,117 +601,31 @@ impl Processor {
         output_path: &Path,
         target: Dimensions,
         split_ratio: f64,
+        crops: [Option<&CropPath>; 2],
     ) -> ProcessResult<PathBuf> {
-        // calculate heights for each section
-        let top_height = (target.height as f64 * split_ratio) as u32;
-        let bottom_height = target.height - top_height;
-
-        // complex filter to scale and stack videos
-        // [0] is top video, [1] is bottom video
-        let filter = format!(
-            "[0:v]scale={}:{},setsar=1[top];\
-             [1:v]scale={}:{},setsar=1[bottom];\
-             [top][bottom]vstack=inputs=2[out]",
-            target.width, top_height,
-            target.width, bottom_height
-        );
-
-        let mut args = vec![
-            "-y".to_string(),
-            "-i".to_string(), top_video.to_str().unwrap().to_string(),
-            "-i".to_string(), bottom_video.to_str().unwrap().to_string(),
-            "-filter_complex".to_string(), filter,
-            "-map".to_string(), "[out]".to_string(),
-            "-map".to_string(), "1:a?".to_string(),  // audio from bottom (user) video
-        ];
+        let layout = Layout::SplitScreen { ratio: split_ratio, crops };
+        let mut args = self.layout_args(&layout, [top_video, bottom_video], target, None);
+        args.extend(["-map".to_string(), "1:a?".to_string()]);  // audio from bottom (user) video
         
-        args.extend(self.get_encoder_args());
-        args.extend([
-            "-c:a".to_string(), "aac".to_string(),
-            "-b:a".to_string(), "192k".to_string(),
-            output_path.to_str().unwrap().to_string(),
-        ]);
-
-        let output = Command::new("ffmpeg")
-            .args(&args)
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            log::error!("composite failed: {}", stderr);
-            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
-        }
+        self.encode(args, self.voice_audio_args(), output_path, "composite").await?;
 
         Ok(output_path.to_path_buf())
     }
 
     // 8b. picture in picture composite
-    // main video fills screen, overlay is small in corner
+    // main video fills screen, overlay is a styled box on top
     pub async fn composite_pip(
         &self,
         main_video: &Path,
         overlay_video: &Path,
         output_path: &Path,
         target: Dimensions,
-        position: OverlayPosition,
-        pip_scale: f64,  // 0.0-1.0, how much of screen pip takes
+        style: &PipStyle,
     ) -> ProcessResult<PathBuf> {
-        let pip_width = (target.width as f64 * pip_scale) as u32;
-        let pip_height = (target.height as f64 * pip_scale) as u32;
-        let padding = 20;  // pixels from edge
-
-        // calculate pip position based on overlay setting
-        let (x_pos, y_pos) = match position {
-            OverlayPosition::TopLeft => (padding, padding),
-            OverlayPosition::TopRight => (target.width as i32 - pip_width as i32 - padding, padding),
-            OverlayPosition::BottomLeft => (padding, target.height as i32 - pip_height as i32 - padding),
-            OverlayPosition::BottomRight => (
-                target.width as i32 - pip_width as i32 - padding,
-                target.height as i32 - pip_height as i32 - padding
-            ),
-            _ => (padding, padding),  // default to top left
-        };
-
-        // filter chain:
-        // 1. scale main to target dimensions
-        // 2. scale overlay to pip size
-        // 3. overlay pip on main
-        let filter = format!(
-            "[0:v]scale={}:{},setsar=1[main];\
-             [1:v]scale={}:{},setsar=1[pip];\
-             [main][pip]overlay={}:{}[out]",
-            target.width, target.height,
-            pip_width, pip_height,
-            x_pos, y_pos
-        );
-
-        let mut args = vec![
-            "-y".to_string(),
-            "-i".to_string(), main_video.to_str().unwrap().to_string(),
-            "-i".to_string(), overlay_video.to_str().unwrap().to_string(),
-            "-filter_complex".to_string(), filter,
-            "-map".to_string(), "[out]".to_string(),
-            "-map".to_string(), "0:a?".to_string(),  // audio from main video
-        ];
+        let mut args = self.layout_args(&Layout::Pip(style), [main_video, overlay_video], target, None);
+        args.extend(["-map".to_string(), "0:a?".to_string()]);  // audio from main video
         
-        args.extend(self.get_encoder_args());
-        args.extend([
-            "-c:a".to_string(), "aac".to_string(),
-            "-b:a".to_string(), "192k".to_string(),
-            output_path.to_str().unwrap().to_string(),
-        ]);
-
-        let output = Command::new("ffmpeg")
-            .args(&args)
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            log::error!("pip composite failed: {}", stderr);
-            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
-        }
+        self.encode(args, self.voice_audio_args(), output_path, "pip composite").await?;
 
         Ok(output_path.to_path_buf())
     }
@@ -387,49 +639,86 @@ impl Processor {
         output_path: &Path,
         target: Dimensions,
         left_ratio: f64,  // how much screen left video takes
+        crops: [Option<&CropPath>; 2],
     ) -> ProcessResult<PathBuf> {
-        let left_width = (target.width as f64 * left_ratio) as u32;
-        let right_width = target.width - left_width;
-
-        // scale both videos and stack horizontally
-        let filter = format!(
-            "[0:v]scale={}:{},setsar=1[left];\
-             [1:v]scale={}:{},setsar=1[right];\
-             [left][right]hstack=inputs=2[out]",
-            left_width, target.height,
-            right_width, target.height
-        );
-
-        let mut args = vec![
-            "-y".to_string(),
-            "-i".to_string(), left_video.to_str().unwrap().to_string(),
-            "-i".to_string(), right_video.to_str().unwrap().to_string(),
-            "-filter_complex".to_string(), filter,
-            "-map".to_string(), "[out]".to_string(),
-            "-map".to_string(), "1:a?".to_string(),  // audio from right (user) video
-        ];
+        let layout = Layout::SideBySide { ratio: left_ratio, crops };
+        let mut args = self.layout_args(&layout, [left_video, right_video], target, None);
+        args.extend(["-map".to_string(), "1:a?".to_string()]);  // audio from right (user) video
         
-        args.extend(self.get_encoder_args());
+        self.encode(args, self.voice_audio_args(), output_path, "side by side composite").await?;
+
+        Ok(output_path.to_path_buf())
+    }
+
+    // 8d. one composite frame as a png, for the layout sliders
+    // seeks are per input (broll is usually shorter than the user video)
+    // and go before -i so it only decodes from the nearest keyframe
+    // clock is where the still sits on the output timeline - an input -ss restarts timestamps at 0,
+    // so both inputs get shifted back up to it or pip timing + animations would all read t=0
+    pub async fn render_still(
+        &self,
+        layout: &Layout<'_>,
+        inputs: [&Path; 2],
+        seeks: [f64; 2],
+        clock: f64,
+        target: Dimensions,
+        output_path: &Path,
+    ) -> ProcessResult<PathBuf> {
+        let mut args = self.layout_args(layout, inputs, target, Some((seeks, clock)));
         args.extend([
-            "-c:a".to_string(), "aac".to_string(),
-            "-b:a".to_string(), "192k".to_string(),
+            "-frames:v".to_string(), "1".to_string(),
+            "-update".to_string(), "1".to_string(),
             output_path.to_str().unwrap().to_string(),
         ]);
 
-        let output = Command::new("ffmpeg")
-            .args(&args)
-            .output()
-            .await?;
+        let output = Command::new("ffmpeg").args(&args).output().await?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            log::error!("side by side composite failed: {}", stderr);
+            log::error!("still frame failed: {}", stderr);
             return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
         }
 
         Ok(output_path.to_path_buf())
     }
 
+    // audio args for the composites, the voice cleanup rides along as -af on the user track
+    // the mapped audio comes straight from an input so it can sit next to the filter_complex
+    fn voice_audio_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(filter) = &self.voice_filter {
+            args.extend(["-af".to_string(), filter.clone()]);
+        }
+        args.extend(self.encoder.audio_args());
+        args
+    }
+
+    // 8e. inputs + layout graph + layers, up to the video -map
+    // layers (captions, logo...) go on top of the layout, their files come in after the two videos
+    // seeks are (per input -ss, output clock) for stills, see 8d
+    fn layout_args(&self, layout: &Layout<'_>, inputs: [&Path; 2], target: Dimensions, seeks: Option<([f64; 2], f64)>) -> Vec<String> {
+        let layered = self.layers.attach(layout.graph(target), "out", layout.split_line(), target, 2);
+
+        let mut args = vec!["-y".to_string()];
+        for (i, input) in inputs.iter().enumerate() {
+            if let Some((seeks, clock)) = seeks {
+                args.extend([
+                    "-ss".to_string(), format!("{:.3}", seeks[i]),
+                    "-itsoffset".to_string(), format!("{:.3}", clock),
+                ]);
+            }
+            args.extend(["-i".to_string(), input.to_str().unwrap().to_string()]);
+        }
+        for input in &layered.extra_inputs {
+            args.extend(["-i".to_string(), input.to_str().unwrap().to_string()]);
+        }
+        args.extend([
+            "-filter_complex".to_string(), layered.filter,
+            "-map".to_string(), format!("[{}]", layered.out_label),
+        ]);
+        args
+    }
+
     // 9a. concatenate multiple clips into one video
     // uses ffmpeg concat demuxer - fast cuz it just copies streams
     pub async fn concat_clips(
@@ -474,49 +763,129 @@ impl Processor {
         Ok(output_path.to_path_buf())
     }
 
+    // 9b. join intro/outro bumpers around the main render
+    // bumpers should already be conformed (7c), this re-encodes once to stitch them
+    // returns where the main render starts in the output, for shifting timed stuff
+    pub async fn join_bumpers(
+        &self,
+        intro: Option<&Path>,
+        main_video: &Path,
+        outro: Option<&Path>,
+        output_path: &Path,
+        transition: Option<&BumperTransition>,
+    ) -> ProcessResult<f64> {
+        let paths: Vec<&Path> = intro.into_iter()
+            .chain(std::iter::once(main_video))
+            .chain(outro)
+            .collect();
+
+        let main_metadata = self.get_metadata(main_video).await?;
+        let mut segments = Vec::new();
+        for path in &paths {
+            let metadata = self.get_metadata(path).await?;
+            segments.push(JoinSegment { duration: metadata.duration, has_audio: metadata.has_audio });
+        }
+
+        let filter = bumpers::join_graph(&segments, main_metadata.fps, transition);
+
+        let mut args = vec!["-y".to_string()];
+        for path in &paths {
+            args.extend(["-i".to_string(), path.to_str().unwrap().to_string()]);
+        }
+        args.extend([
+            "-filter_complex".to_string(), filter,
+            "-map".to_string(), "[vout]".to_string(),
+            "-map".to_string(), "[aout]".to_string(),
+        ]);
+        self.encode(args, self.encoder.audio_args(), output_path, "bumper join").await?;
+
+        let main_index = if intro.is_some() { 1 } else { 0 };
+        Ok(bumpers::segment_offset(&segments, main_index, transition))
+    }
+
     // 10a. add sound effects at specific timestamps
-    // sfx_events is (timestamp_seconds, path_to_sfx_file)
+    // each placement carries its own gain, repeated files are one input split with asplit
+    // huge event lists get pre-rendered to a single track first (10c)
+    // the music bed (if any) gets mixed in the same pass, ducked under the voice
     pub async fn add_sfx(
         &self,
         video_path: &Path,
-        sfx_events: &[(f64, PathBuf)],
+        sfx: &[SfxPlacement],
+        music: Option<&MusicBed>,
         output_path: &Path,
     ) -> ProcessResult<PathBuf> {
-        if sfx_events.is_empty() {
+        if sfx.is_empty() && music.is_none() {
             // no sfx to add, just copy the file
             std::fs::copy(video_path, output_path)?;
             return Ok(output_path.to_path_buf());
         }
 
-        // build complex audio filter
-        // delay each sfx to its timestamp then mix together
+        let metadata = self.get_metadata(video_path).await?;
+        let duration = metadata.duration;
         let mut inputs = vec!["-i".to_string(), video_path.to_str().unwrap().to_string()];
         let mut filter_parts = Vec::new();
-        
-        for (i, (timestamp, sfx_path)) in sfx_events.iter().enumerate() {
-            inputs.push("-i".to_string());
-            inputs.push(sfx_path.to_str().unwrap().to_string());
-            
-            let delay_ms = (timestamp * 1000.0) as u64;
-            filter_parts.push(format!(
-                "[{}:a]adelay={}|{}[sfx{}]",
-                i + 1, delay_ms, delay_ms, i
-            ));
+        let mut next_input = 1;
+
+        // a silent user video comes out of the composite with no audio stream at all,
+        // so the voice is generated silence the length of the video instead of [0:a]
+        let voice = if metadata.has_audio {
+            "[0:a]".to_string()
+        } else {
+            filter_parts.push(format!("anullsrc=r=48000:cl=stereo,atrim=duration={:.3}[silence]", duration));
+            "[silence]".to_string()
+        };
+
+        // sfx bus first, as one [sfxmix]
+        if sfx.len() > sfx::MAX_GRAPH_EVENTS {
+            log::info!("{} sfx events, pre-rendering the sfx track", sfx.len());
+            let track = self.render_sfx_track(sfx, duration).await?;
+            inputs.extend([
+                "-f".to_string(), "f32le".to_string(),
+                "-ar".to_string(), "48000".to_string(),
+                "-ac".to_string(), "2".to_string(),
+                "-i".to_string(), track.to_str().unwrap().to_string(),
+            ]);
+            filter_parts.push(format!("[{}:a]anull[sfxmix]", next_input));
+            next_input += 1;
+        } else if !sfx.is_empty() {
+            let groups = sfx::group_by_file(sfx);
+            for (path, _) in &groups {
+                inputs.extend(["-i".to_string(), path.to_str().unwrap().to_string()]);
+            }
+            filter_parts.push(sfx::sfx_bus_graph(&groups, next_input));
+            next_input += groups.len();
+        }
+
+        // voice feeds the mix, and the ducker if theres music
+        let mut mix_inputs = Vec::new();
+        if let Some(bed) = music {
+            if bed.loop_track {
+                inputs.extend(["-stream_loop".to_string(), "-1".to_string()]);
+            }
+            inputs.extend(["-i".to_string(), bed.path.clone()]);
+
+            filter_parts.push(format!("{}asplit=2[voice][sidechain]", voice));
+            filter_parts.push(bed.bed_chain(next_input, duration));
+            filter_parts.push(bed.duck_chain());
+            mix_inputs.push("[voice]".to_string());
+            mix_inputs.push("[ducked]".to_string());
+        } else {
+            mix_inputs.push(voice);
+        }
+
+        if !sfx.is_empty() {
+            mix_inputs.push("[sfxmix]".to_string());
         }
         
-        // mix all sfx together
-        let sfx_inputs: String = (0..sfx_events.len())
-            .map(|i| format!("[sfx{}]", i))
-            .collect();
+        // mix sfx and music with original audio
+        // normalize=0 so the voice doesnt get turned down for every extra input,
+        // the limiter catches a boom landing on a loud word
         filter_parts.push(format!(
-            "{}amix=inputs={}[sfxmix]",
-            sfx_inputs,
-            sfx_events.len()
+            "{}amix=inputs={}:duration=first:normalize=0,alimiter=limit=0.95:level=disabled[out]",
+            mix_inputs.concat(),
+            mix_inputs.len()
         ));
         
-        // mix sfx with original audio
-        filter_parts.push("[0:a][sfxmix]amix=inputs=2:duration=first[out]".to_string());
-        
         let filter = filter_parts.join(";");
 
         let mut args = inputs;
@@ -525,7 +894,9 @@ impl Processor {
             "-map".to_string(), "0:v".to_string(),
             "-map".to_string(), "[out]".to_string(),
             "-c:v".to_string(), "copy".to_string(),
-            "-c:a".to_string(), "aac".to_string(),
+        ]);
+        args.extend(self.encoder.audio_args());
+        args.extend([
             "-y".to_string(),
             output_path.to_str().unwrap().to_string(),
         ]);
@@ -542,6 +913,98 @@ impl Processor {
 
         Ok(output_path.to_path_buf())
     }
+
+    // 10b. run an audio filter chain over a finished video
+    // video is stream copied, only the audio gets re-encoded
+    pub async fn master_audio(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        audio_filter: &str,
+    ) -> ProcessResult<PathBuf> {
+        // same audio encode as every other pass, the filter already resamples to 48k
+        let mut args: Vec<String> = [
+            "-y",
+            "-i", input_path.to_str().unwrap(),
+            "-map", "0:v",
+            "-map", "0:a?",
+            "-af", audio_filter,
+            "-c:v", "copy",
+        ].iter().map(|s| s.to_string()).collect();
+        args.extend(self.encoder.audio_args());
+        args.push(output_path.to_str().unwrap().to_string());
+
+        let output = Command::new("ffmpeg")
+            .args(&args)
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("audio mastering failed: {}", stderr);
+            return Err(ProcessorError::ProcessingFailed(stderr.to_string()));
+        }
+
+        Ok(output_path.to_path_buf())
+    }
+
+    // 10c. pre-render every sfx into one raw 48k stereo f32 track
+    // each unique file is decoded once, then summed in rust
+    async fn render_sfx_track(&self, sfx: &[SfxPlacement], duration: f64) -> ProcessResult<PathBuf> {
+        const RATE: f64 = 48000.0;
+
+        let groups = sfx::group_by_file(sfx);
+        let mut decoded = Vec::new();
+        for (path, _) in &groups {
+            decoded.push(self.decode_audio(path, RATE as u32).await?);
+        }
+
+        let sounds: Vec<(usize, f32, &[f32])> = groups
+            .iter()
+            .zip(&decoded)
+            .flat_map(|((_, uses), samples)| {
+                uses.iter().map(move |p| (
+                    (p.timestamp.max(0.0) * RATE) as usize,
+                    10f32.powf(p.gain_db as f32 / 20.0),
+                    samples.as_slice(),
+                ))
+            })
+            .collect();
+
+        let track = sfx::mix_sfx_track((duration * RATE).ceil() as usize, &sounds);
+        let bytes: Vec<u8> = track.iter().flat_map(|s| s.to_le_bytes()).collect();
+
+        let track_path = self.temp_dir.join("sfx_track.f32");
+        std::fs::write(&track_path, bytes)?;
+        Ok(track_path)
+    }
+}
+
+// helper for the letterboxed fit scale_video and conform_video share
+fn fit_filter(target: Dimensions) -> String {
+    format!(
+        "scale={}:{}:force_original_aspect_ratio=decrease,pad={}:{}:(ow-iw)/2:(oh-ih)/2:black",
+        target.width, target.height, target.width, target.height
+    )
+}
+
+// scale for proxies: factor = the bigger of the two cover ratios, capped at 1
+// even sides, and the max(2,..) keeps tiny targets from rounding to nothing
+fn proxy_scale(target: Dimensions) -> String {
+    let factor = format!("min(1,max({}/iw,{}/ih))", target.width, target.height);
+    format!(
+        "scale=w='max(2,trunc(iw*{f}/2)*2)':h='max(2,trunc(ih*{f}/2)*2)'",
+        f = factor
+    )
+}
+
+// helper that turns an optional reframe path into a crop filter
+// goes right before the scale so the region gets filled without squashing
+fn crop_prefix(crop: Option<&CropPath>, width: u32, height: u32) -> String {
+    match crop {
+        Some(path) => format!("{},", path.crop_filter(width as f64 / height.max(1) as f64)),
+        None => String::new(),
+    }
 }
 
 // helper to parse fps from ffprobes fraction format
@@ -556,38 +1019,3 @@ fn parse_fps(fps_str: &str) -> f64 {
     }
     30.0  // fallback to 30fps
 }
-
-// detect what gpu encoder is available
-// checks in order of preference: nvidia > vaapi > videotoolbox > cpu
-fn detect_gpu_encoder() -> GpuEncoder {
-    // check for nvidia encoder
-    // nvenc is usually the fastest option
-    if check_encoder_available("h264_nvenc") {
-        return GpuEncoder::Nvenc;
-    }
-    
-    // check for vaapi (amd/intel on linux)
-    if check_encoder_available("h264_vaapi") {
-        return GpuEncoder::Vaapi;
-    }
-    
-    // check for videotoolbox (macos)
-    if check_encoder_available("h264_videotoolbox") {
-        return GpuEncoder::Videotoolbox;
-    }
-    
-    // no gpu encoder found, use cpu
-    GpuEncoder::None
-}
-
-// check if a specific encoder is available in ffmpeg
-fn check_encoder_available(encoder: &str) -> bool {
-    std::process::Command::new("ffmpeg")
-        .args(["-hide_banner", "-encoders"])
-        .output()
-        .map(|output| {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            stdout.contains(encoder)
-        })
-        .unwrap_or(false)
-}
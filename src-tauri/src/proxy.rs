@@ -0,0 +1,231 @@
+// 1a. proxy.rs - cached mezzanine copies of broll sources
+// 1b. 4k downloads get transcoded once down to the job's broll size/fps with short gops,
+// then every cut reads the small file and seeks in a few frames instead of a few seconds
+// 1c. cache is content addressed - same file + same settings = same proxy, across jobs
+// key is a sha256 of the whole file. reading a few GB costs seconds, a proxy encode costs minutes,
+// and a sampled hash could hand two different files the same proxy
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use crate::encoder::{EncoderProfile, QualityPreset};
+use crate::processor::{Dimensions, ProcessorError, Processor};
+
+// 2a. errors
+#[derive(Error, Debug)]
+pub enum ProxyError {
+    #[error("proxy encode failed: {0}")]
+    Encode(#[from] ProcessorError),
+
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+pub type ProxyResult<T> = Result<T, ProxyError>;
+
+// bump when the proxy encode settings change so old proxies stop matching
+const PROXY_VERSION: u32 = 2;
+const DEFAULT_MAX_CACHE_BYTES: u64 = 20 * 1024 * 1024 * 1024;
+
+// 2b. what a proxy gets made at
+#[derive(Debug, Clone, Copy)]
+pub struct ProxySettings {
+    pub size: Dimensions,
+    pub fps: f64,
+}
+
+impl ProxySettings {
+    // keyframe every half second, cuts decode at most that far
+    fn keyframe_interval(&self) -> u32 {
+        ((self.fps / 2.0).round() as u32).max(1)
+    }
+
+    // near lossless x264, only ever read back by our own cuts
+    fn encoder(&self) -> EncoderProfile {
+        EncoderProfile {
+            quality: QualityPreset::Draft,
+            crf: Some(18),
+            keyframe_interval: Some(self.keyframe_interval()),
+            frame_rate: Some(self.fps),
+            ..EncoderProfile::default()
+        }
+    }
+
+    fn tag(&self) -> String {
+        format!("{}x{}_{:.3}_v{}", self.size.width, self.size.height, self.fps, PROXY_VERSION)
+    }
+}
+
+// 3a. the cache folder
+pub struct ProxyCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ProxyCache {
+    pub fn new(dir: impl AsRef<Path>) -> ProxyResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, max_bytes: DEFAULT_MAX_CACHE_BYTES })
+    }
+
+    // the shared one, in the os cache dir so the os can clean it up too
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("godz")
+            .join("proxies")
+    }
+
+    // 3b. the proxy for a source, made if its not cached yet
+    // writes to a .partial file first so a crash or a second job never sees half a proxy
+    pub async fn get_or_create(&self, processor: &Processor, source: &Path, settings: ProxySettings) -> ProxyResult<PathBuf> {
+        let key = {
+            let source = source.to_path_buf();
+            tokio::task::spawn_blocking(move || content_key(&source))
+                .await
+                .map_err(std::io::Error::other)??
+        };
+        let path = self.dir.join(format!("{}_{}.mp4", key, settings.tag()));
+
+        if path.exists() {
+            log::info!("proxy cache hit: {}", source.display());
+            // touch it so pruning keeps the ones still in use
+            if let Ok(file) = File::options().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            return Ok(path);
+        }
+
+        log::info!("making proxy for {} at {}", source.display(), settings.tag());
+        let partial = path.with_extension("partial.mp4");
+        let result = processor
+            .clone()
+            .with_encoder(settings.encoder())
+            .make_proxy(source, &partial, settings.size)
+            .await;
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e.into());
+        }
+        std::fs::rename(&partial, &path)?;
+
+        self.prune()?;
+        Ok(path)
+    }
+
+    // 3c. drop least recently used proxies until the cache fits
+    pub fn prune(&self) -> ProxyResult<u64> {
+        let mut files: Vec<(PathBuf, u64, SystemTime)> = std::fs::read_dir(&self.dir)?
+            .flatten()
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                let modified = meta.modified().ok()?;
+                meta.is_file().then(|| (entry.path(), meta.len(), modified))
+            })
+            .collect();
+        files.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        let mut freed = 0;
+        for (path, size, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total -= size;
+                freed += size;
+            }
+        }
+        Ok(freed)
+    }
+
+    // 3d. wipe it, returns bytes freed
+    pub fn clear(&self) -> ProxyResult<u64> {
+        let mut freed = 0;
+        for entry in std::fs::read_dir(&self.dir)?.flatten() {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if std::fs::remove_file(entry.path()).is_ok() {
+                freed += size;
+            }
+        }
+        Ok(freed)
+    }
+}
+
+// 4a. content hash - sha256 over the whole file, streamed so big files never sit in memory
+// first 16 bytes as hex is plenty for a cache key
+fn content_key(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hash = Sha256::new();
+
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => break,
+            n => hash.update(&buf[..n]),
+        }
+    }
+    Ok(hash.finalize()[..16].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// 5a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_follows_content_not_path() {
+        let dir = std::env::temp_dir().join(format!("godz_proxy_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data: Vec<u8> = (0..3 * 1024 * 1024 + 17).map(|i| (i % 251) as u8).collect();
+
+        let a = dir.join("a.mp4");
+        let b = dir.join("renamed copy.mp4");
+        std::fs::write(&a, &data).unwrap();
+        std::fs::write(&b, &data).unwrap();
+        assert_eq!(content_key(&a).unwrap(), content_key(&b).unwrap());
+
+        // same size, one byte different anywhere = a different file
+        for at in [0, 1024 * 1024 + 5, data.len() - 1] {
+            let mut changed = data.clone();
+            changed[at] ^= 0xff;
+            std::fs::write(&b, &changed).unwrap();
+            assert_ne!(content_key(&a).unwrap(), content_key(&b).unwrap());
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_settings_tag_and_profile() {
+        let settings = ProxySettings { size: Dimensions { width: 1920, height: 1920 }, fps: 30.0 };
+        assert_eq!(settings.tag(), "1920x1920_30.000_v2");
+        let profile = settings.encoder();
+        assert_eq!(profile.keyframe_interval, Some(15));
+        assert_eq!(profile.crf, Some(18));
+    }
+
+    #[test]
+    fn test_proxy_size_is_the_broll_region() {
+        use crate::jobs::{JobConfig, OutputFormat, OverlayPosition};
+
+        // youtube + tiktok split screen: broll is 1920x540 and 1080x960, not 1920x1920
+        let config = JobConfig {
+            output_formats: vec![OutputFormat::YouTube, OutputFormat::TikTok],
+            overlay_position: OverlayPosition::Top,
+            split_ratio: 0.5,
+            ..JobConfig::default()
+        };
+        let settings = config.proxy_settings(24.0);
+        assert_eq!((settings.size.width, settings.size.height), (1920, 960));
+        assert_eq!(settings.fps, 30.0);
+
+        let pip = JobConfig { overlay_position: OverlayPosition::BottomRight, pip_scale: 0.3, ..JobConfig::default() };
+        let settings = pip.proxy_settings(24.0);
+        assert_eq!((settings.size.width, settings.size.height), (576, 324));
+    }
+}
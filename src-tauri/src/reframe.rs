@@ -0,0 +1,403 @@
+// 1a. reframe.rs - keeps the talking head in frame
+// 1b. samples frames from the user video and finds where the person is
+// 1c. then builds a smooth crop path the composite filters can follow
+// faces come from seetaface (rustface crate, pure rust, cpu only). its model is baked into the binary
+// frames where it finds no face fall back to the old skin tone + motion guess, see 6b
+
+use std::path::Path;
+use rustface::{Detector, ImageData};
+use serde::{Deserialize, Serialize};
+use crate::filters::piecewise_linear_expr;
+use crate::processor::{Processor, ProcessorError, ProcessResult, VideoMetadata};
+
+// seetaface frontal detector, bsd-2 (see models/LICENSE-seetaface)
+static FACE_MODEL: &[u8] = include_bytes!("../models/seeta_fd_frontal_v1.0.bin");
+
+// 2a. knobs for the analysis pass
+// defaults are tuned for a single person talking to camera
+#[derive(Debug, Clone)]
+pub struct ReframeConfig {
+    pub sample_fps: f64,      // how many frames per second we look at
+    pub analysis_width: u32,  // frames get shrunk to this before detection
+    pub min_face: u32,        // smallest face in analysis pixels, detector floor is 20
+    pub smoothing: f64,       // 0-1, higher = lazier camera
+    pub deadzone: f64,        // normalized movement ignored before we pan
+    pub max_keyframes: usize, // keeps the crop expression a sane length
+}
+
+impl Default for ReframeConfig {
+    fn default() -> Self {
+        Self {
+            sample_fps: 2.0,
+            analysis_width: 320,
+            min_face: 24,
+            smoothing: 0.8,
+            deadzone: 0.04,
+            max_keyframes: 90,
+        }
+    }
+}
+
+// 3a. one point on the crop path
+// x/y are the subject center, normalized 0-1 in source space
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CropKeyframe {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+// 3b. the full path for one video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CropPath {
+    pub source_width: u32,
+    pub source_height: u32,
+    pub keyframes: Vec<CropKeyframe>,
+}
+
+impl CropPath {
+    // plain center crop - what you get when detection finds nothing
+    pub fn centered(source_width: u32, source_height: u32) -> Self {
+        Self {
+            source_width,
+            source_height,
+            keyframes: vec![CropKeyframe { time: 0.0, x: 0.5, y: 0.5 }],
+        }
+    }
+
+    // 4a. crop filter for a region with the given aspect (width / height)
+    // crop size is fixed, only x/y move so the scale after it stays stable
+    pub fn crop_filter(&self, aspect: f64) -> String {
+        let src_w = self.source_width as f64;
+        let src_h = self.source_height as f64;
+
+        // biggest box with the target aspect that fits in the source
+        let crop_w = even(src_w.min(src_h * aspect));
+        let crop_h = even(src_h.min(src_w / aspect));
+
+        let max_x = src_w - crop_w;
+        let max_y = src_h - crop_h;
+
+        let x_points: Vec<(f64, f64)> = self.keyframes
+            .iter()
+            .map(|k| (k.time, (k.x * src_w - crop_w / 2.0).clamp(0.0, max_x).round()))
+            .collect();
+        let y_points: Vec<(f64, f64)> = self.keyframes
+            .iter()
+            .map(|k| (k.time, (k.y * src_h - crop_h / 2.0).clamp(0.0, max_y).round()))
+            .collect();
+
+        format!(
+            "crop=w={}:h={}:x='{}':y='{}'",
+            crop_w, crop_h,
+            piecewise_linear_expr(&x_points),
+            piecewise_linear_expr(&y_points)
+        )
+    }
+}
+
+// 5a. analyze the user video and build a crop path
+// frames come from ffmpeg at low res so this is quick even for long videos
+pub async fn analyze(
+    processor: &Processor,
+    video_path: &Path,
+    metadata: &VideoMetadata,
+    config: &ReframeConfig,
+) -> ProcessResult<CropPath> {
+    let width = config.analysis_width.max(16);
+    let height = (even(width as f64 * metadata.height as f64 / metadata.width.max(1) as f64) as u32).max(2);
+
+    let frames = processor
+        .sample_frames(video_path, config.sample_fps, width, height)
+        .await?;
+
+    log::info!("reframe: analyzing {} frames at {}x{}", frames.len(), width, height);
+
+    // detection is a few ms a frame, keep it off the async workers
+    let min_face = config.min_face;
+    let centers = tokio::task::spawn_blocking(move || -> ProcessResult<Vec<Option<(f64, f64)>>> {
+        let mut detector = face_detector(min_face)?;
+        let mut centers = Vec::with_capacity(frames.len());
+        let mut prev_luma: Option<Vec<u8>> = None;
+        let (mut faces, mut guesses) = (0, 0);
+
+        for frame in &frames {
+            let luma = to_luma(frame);
+            let center = match detect_face(detector.as_mut(), &luma, width, height) {
+                Some(center) => {
+                    faces += 1;
+                    Some(center)
+                }
+                None => {
+                    let guess = detect_subject(frame, prev_luma.as_deref(), width, height);
+                    guesses += guess.is_some() as usize;
+                    guess
+                }
+            };
+            centers.push(center);
+            prev_luma = Some(luma);
+        }
+
+        log::info!("reframe: {} frames with a face, {} from the skin/motion fallback", faces, guesses);
+        Ok(centers)
+    })
+    .await
+    .map_err(|e| ProcessorError::ProcessingFailed(format!("reframe analysis panicked: {}", e)))??;
+
+    let smoothed = smooth_centers(&centers, config.smoothing);
+    let keyframes = reduce_keyframes(&smoothed, config.sample_fps, config.deadzone, config.max_keyframes);
+
+    if keyframes.is_empty() {
+        return Ok(CropPath::centered(metadata.width, metadata.height));
+    }
+
+    Ok(CropPath {
+        source_width: metadata.width,
+        source_height: metadata.height,
+        keyframes,
+    })
+}
+
+// 6a. the face detector, loaded from the baked in model
+fn face_detector(min_face: u32) -> ProcessResult<Box<dyn Detector>> {
+    let model = rustface::read_model(FACE_MODEL)
+        .map_err(|e| ProcessorError::ProcessingFailed(format!("face model wont load: {}", e)))?;
+    let mut detector = rustface::create_detector_with_model(model);
+    detector.set_min_face_size(min_face.max(20));
+    detector.set_score_thresh(2.0);
+    detector.set_pyramid_scale_factor(0.8);
+    detector.set_slide_window_step(4, 4);
+    Ok(detector)
+}
+
+// center of the biggest face in a grayscale frame, the talking head is the one nearest the camera
+fn detect_face(detector: &mut dyn Detector, luma: &[u8], width: u32, height: u32) -> Option<(f64, f64)> {
+    let faces = detector.detect(&ImageData::new(luma, width, height));
+    let face = faces.iter().max_by_key(|f| f.bbox().width() * f.bbox().height())?;
+    let bbox = face.bbox();
+
+    let x = (bbox.x() as f64 + bbox.width() as f64 / 2.0) / width as f64;
+    let y = (bbox.y() as f64 + bbox.height() as f64 / 2.0) / height as f64;
+    Some((x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+}
+
+// 6b. fallback when no face shows up - profile shots, tiny faces, back to camera
+// skin pixels (ycbcr range check) plus anything that moved since the last frame
+// upper rows get a bonus cuz faces sit above hands most of the time
+// its a guess: warm walls and wood read as skin, which is why it only runs when the detector comes up empty
+// returns None when theres not enough signal to trust
+fn detect_subject(frame: &[u8], prev_luma: Option<&[u8]>, width: u32, height: u32) -> Option<(f64, f64)> {
+    let (w, h) = (width as usize, height as usize);
+    let mut total = 0.0;
+    let mut sum_x = 0.0;
+    let mut sum_y = 0.0;
+
+    for y in 0..h {
+        let row_bias = 1.0 + 0.5 * (1.0 - y as f64 / h as f64);
+
+        for x in 0..w {
+            let i = y * w + x;
+            let (r, g, b) = (frame[i * 3] as f64, frame[i * 3 + 1] as f64, frame[i * 3 + 2] as f64);
+
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+            let cb = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+            let cr = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+
+            let skin = luma > 40.0 && (77.0..=127.0).contains(&cb) && (133.0..=173.0).contains(&cr);
+            let moved = prev_luma
+                .map(|prev| (prev[i] as f64 - luma).abs() > 12.0)
+                .unwrap_or(false);
+
+            let mut weight = 0.0;
+            if skin {
+                weight += 1.0;
+            }
+            if moved {
+                weight += if skin { 1.0 } else { 0.3 };
+            }
+            if weight == 0.0 {
+                continue;
+            }
+
+            weight *= row_bias;
+            total += weight;
+            sum_x += weight * (x as f64 + 0.5);
+            sum_y += weight * (y as f64 + 0.5);
+        }
+    }
+
+    // need at least half a percent of the frame to say anything
+    if total < (w * h) as f64 * 0.005 {
+        return None;
+    }
+
+    Some((sum_x / total / w as f64, sum_y / total / h as f64))
+}
+
+fn to_luma(frame: &[u8]) -> Vec<u8> {
+    frame
+        .chunks_exact(3)
+        .map(|px| (0.299 * px[0] as f64 + 0.587 * px[1] as f64 + 0.114 * px[2] as f64) as u8)
+        .collect()
+}
+
+// 6c. fill gaps and smooth the raw detections
+// missing frames hold the last known spot (or center at the start)
+// ema runs forward then backward so the pan doesnt lag behind the person
+fn smooth_centers(raw: &[Option<(f64, f64)>], smoothing: f64) -> Vec<(f64, f64)> {
+    let alpha = 1.0 - smoothing.clamp(0.0, 0.99);
+
+    let mut last = raw.iter().flatten().next().copied().unwrap_or((0.5, 0.5));
+    let filled: Vec<(f64, f64)> = raw
+        .iter()
+        .map(|c| {
+            if let Some(c) = c {
+                last = *c;
+            }
+            last
+        })
+        .collect();
+
+    let ema = |points: &mut dyn Iterator<Item = &(f64, f64)>| -> Vec<(f64, f64)> {
+        let mut out = Vec::new();
+        let mut state: Option<(f64, f64)> = None;
+        for &(x, y) in points {
+            let next = match state {
+                Some((sx, sy)) => (sx + alpha * (x - sx), sy + alpha * (y - sy)),
+                None => (x, y),
+            };
+            state = Some(next);
+            out.push(next);
+        }
+        out
+    };
+
+    let forward = ema(&mut filled.iter());
+    let mut backward = ema(&mut forward.iter().rev());
+    backward.reverse();
+    backward
+}
+
+// 6d. turn per-sample centers into keyframes
+// only emits a point when the subject drifts past the deadzone
+// so the camera holds still instead of wobbling every sample
+fn reduce_keyframes(centers: &[(f64, f64)], sample_fps: f64, deadzone: f64, max_keyframes: usize) -> Vec<CropKeyframe> {
+    if centers.is_empty() {
+        return Vec::new();
+    }
+
+    let step = 1.0 / sample_fps.max(0.1);
+    let mut keyframes = vec![CropKeyframe { time: 0.0, x: centers[0].0, y: centers[0].1 }];
+
+    for (i, &(x, y)) in centers.iter().enumerate().skip(1) {
+        let last = keyframes[keyframes.len() - 1];
+        if (x - last.x).abs() > deadzone || (y - last.y).abs() > deadzone {
+            keyframes.push(CropKeyframe { time: i as f64 * step, x, y });
+        }
+    }
+
+    // too many points - thin them out evenly, always keep the ends
+    if max_keyframes >= 2 && keyframes.len() > max_keyframes {
+        let stride = (keyframes.len() as f64 / max_keyframes as f64).ceil() as usize;
+        let last = keyframes[keyframes.len() - 1];
+        keyframes = keyframes.into_iter().step_by(stride).collect();
+        if keyframes.last() != Some(&last) {
+            keyframes.push(last);
+        }
+    }
+
+    keyframes
+}
+
+// ffmpeg wants even crop sizes for yuv420
+fn even(value: f64) -> f64 {
+    ((value / 2.0).floor() * 2.0).max(2.0)
+}
+
+// 7a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crop_filter_centered() {
+        // 16:9 source into a 9:8 region (tiktok half) keeps full height
+        let path = CropPath::centered(1920, 1080);
+        let filter = path.crop_filter(1080.0 / 960.0);
+        assert_eq!(filter, "crop=w=1214:h=1080:x='353.000':y='0.000'");
+    }
+
+    #[test]
+    fn test_reduce_keyframes_deadzone() {
+        let centers = vec![(0.5, 0.5), (0.51, 0.5), (0.52, 0.5), (0.7, 0.5)];
+        let keyframes = reduce_keyframes(&centers, 2.0, 0.04, 90);
+        assert_eq!(keyframes.len(), 2);
+        assert_eq!(keyframes[1].time, 1.5);
+    }
+
+    #[test]
+    fn test_smooth_holds_missing_detections() {
+        let raw = vec![None, Some((0.2, 0.4)), None, None];
+        let smoothed = smooth_centers(&raw, 0.0);
+        assert_eq!(smoothed, vec![(0.2, 0.4); 4]);
+    }
+
+    // solid frame with one square patch, rgb
+    fn frame_with_patch(w: usize, h: usize, bg: [u8; 3], patch: [u8; 3], at: (usize, usize), size: usize) -> Vec<u8> {
+        let mut frame = bg.repeat(w * h);
+        for y in at.1..(at.1 + size).min(h) {
+            for x in at.0..(at.0 + size).min(w) {
+                let i = (y * w + x) * 3;
+                frame[i..i + 3].copy_from_slice(&patch);
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn test_detector_finds_a_real_face() {
+        // 200x112 crop of a real photo, one man a bit right of center
+        let pgm = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/face_200x112.pgm")).unwrap();
+        let luma = &pgm[pgm.len() - 200 * 112..];
+
+        let mut detector = face_detector(20).unwrap();
+        let (x, y) = detect_face(detector.as_mut(), luma, 200, 112).unwrap();
+        assert!((0.53..0.63).contains(&x), "x was {}", x);
+        assert!((0.5..0.66).contains(&y), "y was {}", y);
+    }
+
+    #[test]
+    fn test_detector_ignores_faceless_frames() {
+        let mut detector = face_detector(20).unwrap();
+
+        // flat grey, a warm wall that the skin check loves, and noisy texture
+        let grey = vec![128u8; 320 * 180];
+        let wall = to_luma(&frame_with_patch(320, 180, [200, 120, 60], [198, 134, 66], (40, 40), 120));
+        let noise: Vec<u8> = (0..320 * 180u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+
+        for luma in [grey, wall, noise] {
+            assert_eq!(detect_face(detector.as_mut(), &luma, 320, 180), None);
+        }
+    }
+
+    #[test]
+    fn test_fallback_across_skin_tones() {
+        // light through deep skin on a neutral backdrop, patch centered at (0.25, 0.25)
+        for skin in [[224, 172, 140], [141, 85, 36], [92, 58, 40], [60, 40, 30]] {
+            let frame = frame_with_patch(80, 80, [40, 60, 90], skin, (10, 10), 20);
+            let (x, y) = detect_subject(&frame, None, 80, 80).unwrap();
+            assert!((x - 0.25).abs() < 0.01 && (y - 0.25).abs() < 0.01, "{:?} -> {:?}", skin, (x, y));
+        }
+    }
+
+    #[test]
+    fn test_fallback_follows_motion_and_gives_up_without_signal() {
+        let still = frame_with_patch(80, 80, [40, 60, 90], [40, 60, 90], (0, 0), 0);
+        assert_eq!(detect_subject(&still, Some(&to_luma(&still)), 80, 80), None);
+
+        // a grey shirt moved in on the right, nothing skin colored anywhere
+        let moved = frame_with_patch(80, 80, [40, 60, 90], [150, 150, 150], (50, 30), 20);
+        let (x, _) = detect_subject(&moved, Some(&to_luma(&still)), 80, 80).unwrap();
+        assert!((x - 0.75).abs() < 0.01);
+    }
+}
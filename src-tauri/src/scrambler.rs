@@ -7,6 +7,7 @@ use std::path::{Path, PathBuf};
 use rand::seq::SliceRandom;
 use rand::Rng;
 use crate::processor::Processor;
+use crate::encoder::EncoderProfile;
 use crate::downloader::VideoInfo;
 
 // 2a. config for how we scramble clips
@@ -18,6 +19,7 @@ pub struct ScrambleConfig {
     pub min_clip_duration: f64,  // shortest clip allowed
     pub duration_variance: f64,  // randomness in length (0-1)
     pub randomize_order: bool,   // shuffle clips from diff sources
+    pub frame_accurate: bool,    // snap cuts to the source frames + use the probed lengths
 }
 
 impl Default for ScrambleConfig {
@@ -27,6 +29,7 @@ impl Default for ScrambleConfig {
             min_clip_duration: 1.5,  // shorter looks choppy
             duration_variance: 0.5,
             randomize_order: true,   // always shuffle for uniqueness
+            frame_accurate: false,   // plain cuts are a bit faster
         }
     }
 }
@@ -56,9 +59,10 @@ pub struct Scrambler {
 }
 
 impl Scrambler {
-    pub fn new(config: ScrambleConfig, temp_dir: impl AsRef<Path>) -> Result<Self, String> {
+    // takes the job's processor so cuts share its gpu + fallback state
+    pub fn new(config: ScrambleConfig, temp_dir: impl AsRef<Path>, processor: Processor) -> Result<Self, String> {
         let temp_dir = temp_dir.as_ref().to_path_buf();
-        let processor = Processor::new(&temp_dir)
+        std::fs::create_dir_all(&temp_dir)
             .map_err(|e| e.to_string())?;
         
         Ok(Self {
@@ -68,6 +72,12 @@ impl Scrambler {
         })
     }
 
+    // 4b. encoder for the cut clips, previews use the fast one
+    pub fn with_encoder(mut self, encoder: EncoderProfile) -> Self {
+        self.processor = self.processor.with_encoder(encoder);
+        self
+    }
+
     // 5a. plan_clips - figures out where to cut
     // 5b. takes total duration we need (user video length)
     // 5c. returns list of clip specs
@@ -76,6 +86,14 @@ impl Scrambler {
         let mut rng = rand::thread_rng();
         let mut clips = Vec::new();
         let mut total_duration = 0.0;
+
+        // frame accurate cuts never reuse a spec, so plan a clip's worth of headroom
+        // for the frame rounding + probed lengths coming in a little under plan
+        let target_duration = if self.config.frame_accurate {
+            target_duration + self.config.min_clip_duration
+        } else {
+            target_duration
+        };
         
         // collect all possible clip start positions
         let mut all_positions: Vec<(usize, f64)> = Vec::new();
@@ -169,11 +187,17 @@ impl Scrambler {
     // 7a. cut_clips - actually cuts the videos
     // 7b. calls ffmpeg for each clip
     // 7c. mutes audio (crucial for avoiding detection)
+    // target_duration is the user video length, frame accurate mode trims the last clip to it
     pub async fn cut_clips(
         &self,
         sources: &[VideoInfo],
         clip_specs: &[ClipSpec],
+        target_duration: f64,
     ) -> Result<Vec<CutClip>, String> {
+        if self.config.frame_accurate {
+            return self.cut_clips_frame_accurate(sources, clip_specs, target_duration).await;
+        }
+
         let mut cut_clips = Vec::new();
         
         for (i, spec) in clip_specs.iter().enumerate() {
@@ -205,6 +229,112 @@ impl Scrambler {
         Ok(cut_clips)
     }
 
+    // 7d. the frame accurate version
+    // in/out snap to each source's frame grid, every clip gets probed after the cut
+    // and the running total of real lengths decides how many frames the last one gets
+    // keeps going until the clips cover the target - a tail shorter than min_clip_duration
+    // regrows the clip before it instead of becoming a sliver. specs are never reused
+    // (plan_clips leaves headroom for this), running out means the sources really are too short
+    async fn cut_clips_frame_accurate(
+        &self,
+        sources: &[VideoInfo],
+        clip_specs: &[ClipSpec],
+        target_duration: f64,
+    ) -> Result<Vec<CutClip>, String> {
+        if clip_specs.is_empty() {
+            return Err("no clips planned to cut".to_string());
+        }
+
+        let mut fps_cache: Vec<Option<f64>> = vec![None; sources.len()];
+        let mut cut_clips: Vec<CutClip> = Vec::new();
+        let mut placed: Vec<ClipSpec> = Vec::new();
+        let mut total = 0.0;
+        let mut specs = clip_specs.iter();
+        let mut last_stuck = false;
+
+        // every spec once plus a few regrows, anything past that means the sources are broken
+        let max_cuts = clip_specs.len() * 2 + 8;
+
+        for _ in 0..max_cuts {
+            let remaining = target_duration - total;
+            if remaining <= END_SLACK {
+                break;
+            }
+
+            // short tail - pull the last clip back off and cut it again longer
+            let regrow = !last_stuck && remaining < self.config.min_clip_duration && !placed.is_empty();
+            let (spec, budget) = if regrow {
+                let last = placed.pop().unwrap();
+                let clip = cut_clips.pop().unwrap();
+                total -= clip.duration;
+                let spec = ClipSpec { duration: clip.duration + remaining, ..last };
+                (spec, clip.duration + remaining)
+            } else {
+                match specs.next() {
+                    Some(spec) => (spec.clone(), remaining),
+                    None => break,
+                }
+            };
+            let i = cut_clips.len();
+
+            let source = &sources[spec.source_idx];
+            let fps = match fps_cache[spec.source_idx] {
+                Some(fps) => fps,
+                None => {
+                    let metadata = self.processor.get_metadata(&source.path)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    let fps = if metadata.fps > 0.0 { metadata.fps } else { 30.0 };
+                    fps_cache[spec.source_idx] = Some(fps);
+                    fps
+                }
+            };
+
+            let (start, duration) = fit_clip(&spec, budget, self.config.min_clip_duration, source.duration);
+            let (start_frame, frames) = if duration >= budget - END_SLACK {
+                // the closing clip rounds up so the total never lands a frame short
+                (frame_span(start, duration, fps).0, frames_covering(duration, fps))
+            } else {
+                frame_span(start, duration, fps)
+            };
+            let clip_path = self.temp_dir.join(format!("clip_{:04}.mp4", i));
+
+            log::info!("cutting clip {} from {} @ frame {} ({} frames at {:.3}fps)",
+                i, source.title, start_frame, frames, fps);
+
+            let cut_duration = self.processor.cut_clip_frames(
+                &source.path,
+                &clip_path,
+                start_frame,
+                frames,
+                fps,
+                true,  // mute audio
+            ).await.map_err(|e| e.to_string())?;
+
+            if (cut_duration - frames as f64 / fps).abs() > 0.5 / fps {
+                log::warn!("clip {} came out {:.3}s, planned {:.3}s", i, cut_duration, frames as f64 / fps);
+            }
+
+            // a regrow that couldnt grow (source ran out) moves on to the next spec
+            last_stuck = regrow && cut_duration <= budget - remaining + END_SLACK;
+
+            total += cut_duration;
+            placed.push(ClipSpec { start_time: start, duration: cut_duration, ..spec });
+            cut_clips.push(CutClip {
+                path: clip_path,
+                source_url: source.source_url.clone(),
+                duration: cut_duration,
+            });
+        }
+
+        if target_duration - total > END_SLACK {
+            return Err(format!("broll came up {:.2}s short of the {:.2}s video", target_duration - total, target_duration));
+        }
+
+        log::info!("cut {} clips, {:.3}s total (needed {:.3}s)", cut_clips.len(), total, target_duration);
+        Ok(cut_clips)
+    }
+
     // 8a. concat_clips - joins all clips into one video
     // uses ffmpeg concat demuxer which is fast
     pub async fn concat_clips(&self, clips: &[CutClip]) -> Result<PathBuf, String> {
@@ -217,6 +347,35 @@ impl Scrambler {
     }
 }
 
+// well under a frame at any real fps, closer than this counts as covered
+const END_SLACK: f64 = 0.001;
+
+// 9a. a start time + length as whole frames - (first frame, frame count)
+// always at least one frame, a clip shorter than that cant exist
+fn frame_span(start: f64, duration: f64, fps: f64) -> (u64, u64) {
+    let start_frame = (start.max(0.0) * fps).round() as u64;
+    let frames = ((duration * fps).round() as u64).max(1);
+    (start_frame, frames)
+}
+
+// 9b. whole frames needed to cover a length, rounding up
+fn frames_covering(duration: f64, fps: f64) -> u64 {
+    ((duration * fps - 1e-6).ceil() as u64).max(1)
+}
+
+// 9c. where and how long one clip gets cut, given what the target still needs (budget)
+// trims to the budget, but a leftover under min_clip gets folded into this clip
+// so the next one never has to be a sliver. stays inside the source by sliding the start back
+fn fit_clip(spec: &ClipSpec, budget: f64, min_clip: f64, source_duration: f64) -> (f64, f64) {
+    let mut duration = spec.duration.min(budget);
+    if budget - duration < min_clip {
+        duration = budget;
+    }
+    let duration = duration.min(source_duration);
+    let start = spec.start_time.min(source_duration - duration).max(0.0);
+    (start, duration)
+}
+
 // ============================================
 // WHY THIS WORKS - content id avoidance notes
 // ============================================
@@ -247,3 +406,93 @@ impl Scrambler {
 // this isnt 100% bulletproof but it works most of the time
 // the key insight is were making something genuinely new
 // from the combination of sources
+
+// 10a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(start_time: f64, duration: f64) -> ClipSpec {
+        ClipSpec { source_idx: 0, start_time, duration }
+    }
+
+    #[test]
+    fn test_frame_accurate_plans_headroom_without_repeats() {
+        let temp = std::env::temp_dir().join(format!("godz_scramble_test_{}", std::process::id()));
+        let processor = Processor::new(&temp, &crate::capabilities::Capabilities::missing()).unwrap();
+        let config = ScrambleConfig { frame_accurate: true, ..ScrambleConfig::default() };
+        let scrambler = Scrambler::new(config, &temp, processor).unwrap();
+
+        let sources: Vec<VideoInfo> = (0..3)
+            .map(|i| VideoInfo {
+                path: PathBuf::from(format!("src{}.mp4", i)),
+                title: format!("src{}", i),
+                duration: 60.0,
+                source_url: String::new(),
+            })
+            .collect();
+
+        let specs = scrambler.plan_clips(&sources, 30.0);
+        let total: f64 = specs.iter().map(|s| s.duration).sum();
+        assert!(total >= 31.5, "planned {}", total);
+
+        // nothing planned twice - the cut pass relies on that
+        for (i, a) in specs.iter().enumerate() {
+            for b in &specs[i + 1..] {
+                let overlap = a.source_idx == b.source_idx
+                    && a.start_time < b.start_time + b.duration
+                    && b.start_time < a.start_time + a.duration;
+                assert!(!overlap, "{:?} overlaps {:?}", a, b);
+            }
+        }
+
+        let _ = std::fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_frame_span_rounds_to_the_grid() {
+        // 1.02s at 30fps is frame 30.6 -> 31, 2.49s is 74.7 -> 75 frames
+        assert_eq!(frame_span(1.02, 2.49, 30.0), (31, 75));
+        // ntsc rates land on the nearest frame too
+        assert_eq!(frame_span(10.0, 1.0, 30000.0 / 1001.0), (300, 30));
+    }
+
+    #[test]
+    fn test_frame_span_from_zero_and_one_frame_minimum() {
+        assert_eq!(frame_span(0.0, 2.0, 24.0), (0, 48));
+        assert_eq!(frame_span(-0.5, 2.0, 24.0).0, 0);
+        assert_eq!(frame_span(0.0, 0.001, 30.0), (0, 1));
+        assert_eq!(frame_span(0.0, 0.0, 30.0), (0, 1));
+    }
+
+    #[test]
+    fn test_frames_covering_rounds_up() {
+        assert_eq!(frames_covering(1.0, 30.0), 30);
+        assert_eq!(frames_covering(1.01, 30.0), 31);
+        assert_eq!(frames_covering(0.0, 30.0), 1);
+    }
+
+    #[test]
+    fn test_last_clip_trims_to_the_target() {
+        // 2s left, clip planned at 4s - cut 2s
+        assert_eq!(fit_clip(&spec(10.0, 4.0), 2.0, 1.5, 60.0), (10.0, 2.0));
+        // plenty left - clip keeps its planned length
+        assert_eq!(fit_clip(&spec(10.0, 3.0), 20.0, 1.5, 60.0), (10.0, 3.0));
+    }
+
+    #[test]
+    fn test_short_tail_folds_into_the_clip() {
+        // 5s left with a 4s clip would leave a 1s sliver under the 1.5s minimum
+        assert_eq!(fit_clip(&spec(10.0, 4.0), 5.0, 1.5, 60.0), (10.0, 5.0));
+        // 6s left leaves 2s, enough for a clip of its own
+        assert_eq!(fit_clip(&spec(10.0, 4.0), 6.0, 1.5, 60.0), (10.0, 4.0));
+    }
+
+    #[test]
+    fn test_fit_clip_stays_inside_the_source() {
+        // growing past the end slides the start back
+        assert_eq!(fit_clip(&spec(8.0, 3.0), 4.0, 1.5, 10.0), (6.0, 4.0));
+        // source shorter than what we want - take all of it
+        assert_eq!(fit_clip(&spec(1.0, 4.0), 10.0, 1.5, 3.0), (0.0, 3.0));
+    }
+}
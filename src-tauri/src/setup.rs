@@ -5,6 +5,7 @@
 
 use std::path::PathBuf;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 
 // 2a. ensure yt-dlp is available
 // checks PATH first, then app data dir, downloads if needed
@@ -134,3 +135,119 @@ pub async fn get_ytdlp_command() -> Result<String, String> {
     Ok(path.to_string_lossy().to_string())
 }
 
+// 6a. find whisper.cpp for captions
+// checks PATH for the usual binary names, then our bin dir
+// we dont auto-download this one, builds are too platform specific
+pub fn find_whisper_binary() -> Option<PathBuf> {
+    for name in ["whisper-cli", "whisper-cpp"] {
+        if let Ok(path) = which::which(name) {
+            return Some(path);
+        }
+    }
+
+    let app_dir = get_app_bin_dir().ok()?;
+    let bundled = if cfg!(windows) {
+        app_dir.join("whisper-cli.exe")
+    } else {
+        app_dir.join("whisper-cli")
+    };
+
+    if bundled.exists() {
+        Some(bundled)
+    } else {
+        None
+    }
+}
+
+// 6b. where a whisper model lives on disk
+// names are the ggml ones, like "base" or "small.en"
+// the name ends up in a path and a url, so only plain model names get through
+pub fn whisper_model_path(name: &str) -> Result<PathBuf, String> {
+    if !valid_model_name(name) {
+        return Err(format!("not a whisper model name: {}", name));
+    }
+
+    let data_dir = dirs::data_dir()
+        .ok_or("couldnt find data directory")?;
+
+    Ok(data_dir.join("godz").join("models").join(format!("ggml-{}.bin", name)))
+}
+
+// letters, digits, dot, dash, underscore - and no ".." so it cant climb out of models/
+fn valid_model_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains("..")
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+// 6c. make sure a whisper model is downloaded
+// grabs it from the whisper.cpp huggingface repo the first time
+// streams to a .partial file and renames at the end, a cut off download never looks finished
+pub async fn ensure_whisper_model(name: &str) -> Result<PathBuf, String> {
+    let model_path = whisper_model_path(name)?;
+    if model_path.exists() {
+        return Ok(model_path);
+    }
+
+    if let Some(parent) = model_path.parent() {
+        fs::create_dir_all(parent).await
+            .map_err(|e| format!("failed to create models dir: {}", e))?;
+    }
+
+    let url = format!(
+        "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-{}.bin",
+        name
+    );
+    log::info!("downloading whisper model from: {}", url);
+
+    let mut response = reqwest::get(&url).await
+        .map_err(|e| format!("download failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("download failed with status: {}", response.status()));
+    }
+
+    let partial = model_path.with_extension("bin.partial");
+    let result = async {
+        let mut file = fs::File::create(&partial).await
+            .map_err(|e| format!("failed to create file: {}", e))?;
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| format!("failed to read download: {}", e))?
+        {
+            file.write_all(&chunk).await
+                .map_err(|e| format!("failed to write file: {}", e))?;
+        }
+        file.flush().await
+            .map_err(|e| format!("failed to write file: {}", e))
+    }.await;
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    fs::rename(&partial, &model_path).await
+        .map_err(|e| format!("failed to move model into place: {}", e))?;
+
+    log::info!("whisper model saved to: {}", model_path.display());
+    Ok(model_path)
+}
+
+// 7a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_names_cant_escape() {
+        assert!(valid_model_name("base"));
+        assert!(valid_model_name("small.en"));
+        assert!(valid_model_name("large-v3_turbo"));
+
+        assert!(!valid_model_name(""));
+        assert!(!valid_model_name("../../x"));
+        assert!(!valid_model_name("base/../../x"));
+        assert!(!valid_model_name("a..b"));
+        assert!(!valid_model_name("base?x=1"));
+        assert!(whisper_model_path("../../x").is_err());
+    }
+}
@@ -1,15 +1,21 @@
 // 1a. sfx.rs - sound effects library
-// 1b. handles the 10 major sfx types
-// 1c. user provides a folder, we match files to types
+// 1b. the 10 major sfx types are built in, more come from sfx_categories
+// 1c. user provides a folder, we match files to categories
+// 1d. filenames first, then sfx_analysis listens to whatever isnt named,
+// and a manifest in the folder remembers results + user overrides
 // this part is kinda extra but makes videos pop
 
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
+use crate::sfx_analysis::{self, AudioFeatures};
+use crate::sfx_categories::{PlacementRules, SfxCategories, SfxCategory, SfxTrigger};
 
 // 2a. the 10 sfx types we support
 // named after the sound they make cuz thats how ppl name files
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SfxType {
     Ching,      // cash register, coin drop
     Riser,      // tension building swoosh UP
@@ -40,32 +46,28 @@ impl SfxType {
     }
 
     // 3a. match filename to sfx type
-    // looks for keywords in the filename
+    // looks for keywords in the filename, first type in all() order wins
     pub fn from_filename(filename: &str) -> Option<SfxType> {
         let lower = filename.to_lowercase();
-        
-        if lower.contains("ching") || lower.contains("coin") || lower.contains("cash") {
-            Some(SfxType::Ching)
-        } else if lower.contains("riser") || lower.contains("rise") || lower.contains("buildup") {
-            Some(SfxType::Riser)
-        } else if lower.contains("fall") || lower.contains("drop") {
-            Some(SfxType::Faller)
-        } else if lower.contains("whoosh") || lower.contains("swoosh") || lower.contains("swipe") {
-            Some(SfxType::Whoosh)
-        } else if lower.contains("pop") || lower.contains("blip") || lower.contains("bubble") {
-            Some(SfxType::Pop)
-        } else if lower.contains("boom") || lower.contains("bass") || lower.contains("impact") {
-            Some(SfxType::Boom)
-        } else if lower.contains("glitch") || lower.contains("error") || lower.contains("stutter") {
-            Some(SfxType::Glitch)
-        } else if lower.contains("click") || lower.contains("tap") || lower.contains("button") {
-            Some(SfxType::Click)
-        } else if lower.contains("sparkle") || lower.contains("magic") || lower.contains("shimmer") {
-            Some(SfxType::Sparkle)
-        } else if lower.contains("thud") || lower.contains("land") || lower.contains("heavy") {
-            Some(SfxType::Thud)
-        } else {
-            None
+        Self::all()
+            .iter()
+            .copied()
+            .find(|t| t.keywords().iter().any(|k| lower.contains(k)))
+    }
+
+    // keywords for the built-in categories
+    pub fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            SfxType::Ching => &["ching", "coin", "cash"],
+            SfxType::Riser => &["riser", "rise", "buildup"],
+            SfxType::Faller => &["fall", "drop"],
+            SfxType::Whoosh => &["whoosh", "swoosh", "swipe"],
+            SfxType::Pop => &["pop", "blip", "bubble"],
+            SfxType::Boom => &["boom", "bass", "impact"],
+            SfxType::Glitch => &["glitch", "error", "stutter"],
+            SfxType::Click => &["click", "tap", "button"],
+            SfxType::Sparkle => &["sparkle", "magic", "shimmer"],
+            SfxType::Thud => &["thud", "land", "heavy"],
         }
     }
 
@@ -84,6 +86,47 @@ impl SfxType {
         }
     }
 
+    // 3b. default mix level per type (dB)
+    // booms and risers are mastered hot and long, clicks and pops are tiny
+    pub fn default_gain_db(&self) -> f64 {
+        match self {
+            SfxType::Ching => -8.0,
+            SfxType::Riser => -12.0,
+            SfxType::Faller => -12.0,
+            SfxType::Whoosh => -10.0,
+            SfxType::Pop => -6.0,
+            SfxType::Boom => -11.0,
+            SfxType::Glitch => -12.0,
+            SfxType::Click => -5.0,
+            SfxType::Sparkle => -12.0,
+            SfxType::Thud => -9.0,
+        }
+    }
+
+    // 3c. where the auto placer puts each built-in
+    // risers lead into the cut, impacts land on emphasis, sparkles fill gaps
+    pub fn default_placement(&self) -> PlacementRules {
+        let rules = |triggers: &[SfxTrigger], offset: f64, min_spacing: f64, max_per_minute: f64, over_speech: bool| PlacementRules {
+            triggers: triggers.to_vec(),
+            offset,
+            min_spacing,
+            max_per_minute,
+            over_speech,
+        };
+
+        match self {
+            SfxType::Whoosh => rules(&[SfxTrigger::Transition], -0.15, 2.0, 12.0, true),
+            SfxType::Riser => rules(&[SfxTrigger::Transition], -1.5, 15.0, 2.0, true),
+            SfxType::Faller => rules(&[SfxTrigger::Transition], 0.0, 10.0, 3.0, true),
+            SfxType::Glitch => rules(&[SfxTrigger::Transition], 0.0, 8.0, 4.0, true),
+            SfxType::Boom => rules(&[SfxTrigger::Emphasis], 0.0, 12.0, 3.0, true),
+            SfxType::Thud => rules(&[SfxTrigger::Emphasis], 0.0, 6.0, 4.0, true),
+            SfxType::Pop => rules(&[SfxTrigger::Emphasis], 0.0, 4.0, 6.0, false),
+            SfxType::Sparkle => rules(&[SfxTrigger::Interval], 0.0, 20.0, 2.0, false),
+            SfxType::Ching | SfxType::Click => PlacementRules::default(),
+        }
+    }
+
     pub fn description(&self) -> &str {
         match self {
             SfxType::Ching => "cash register / coin",
@@ -101,26 +144,147 @@ impl SfxType {
 }
 
 // 4a. sfx library loaded from folder
+// sounds are keyed by category name
 pub struct SfxLibrary {
-    sounds: HashMap<SfxType, Vec<PathBuf>>,
+    categories: SfxCategories,
+    sounds: HashMap<String, Vec<PathBuf>>,
+    files: Vec<SfxFileInfo>,
+}
+
+// 4b. how a file ended up where it is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssignmentSource {
+    Manifest,    // user picked it (or excluded it)
+    Filename,    // keyword in the name
+    Analysis,    // classifier was confident enough
+    Unassigned,  // no idea, or a loop thats too long to be an sfx
+}
+
+// 4c. one scanned file, this is what the ui shows for the library
+#[derive(Debug, Clone, Serialize)]
+pub struct SfxFileInfo {
+    pub path: PathBuf,
+    pub category: Option<String>,
+    pub source: AssignmentSource,
+    pub suggested: Option<SfxType>,  // classifier's guess, even when it wasnt used
+    pub confidence: f32,
+}
+
+// 4d. saved next to the sounds as sfx_manifest.json
+// keys are paths relative to the folder with forward slashes
+pub const MANIFEST_NAME: &str = "sfx_manifest.json";
+
+// classifier guesses under this are only suggestions
+const ANALYSIS_CONFIDENCE: f32 = 0.75;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SfxManifest {
+    #[serde(default)]
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ManifestEntry {
+    pub size: u64,                         // analysis is redone when the file changes
+    pub features: Option<AudioFeatures>,
+    pub suggested: Option<SfxType>,
+    pub confidence: f32,
+    pub assigned: Option<String>,          // user override, a category name
+    pub excluded: bool,                    // user says this isnt an sfx
+}
+
+impl ManifestEntry {
+    fn has_override(&self) -> bool {
+        self.assigned.is_some() || self.excluded
+    }
+}
+
+impl SfxManifest {
+    // 4e. missing or broken manifest = start fresh, never fatal
+    pub fn load(folder: &Path) -> Self {
+        std::fs::read_to_string(folder.join(MANIFEST_NAME))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, folder: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(folder.join(MANIFEST_NAME), json)
+    }
+
+    // 4f. user override for one file, None + !excluded clears it
+    pub fn set_override(folder: &Path, file: &Path, category: Option<String>, excluded: bool) -> std::io::Result<()> {
+        let mut manifest = Self::load(folder);
+        let entry = manifest.files.entry(manifest_key(folder, file)).or_default();
+        entry.assigned = category;
+        entry.excluded = excluded;
+        manifest.save(folder)
+    }
+}
+
+fn manifest_key(folder: &Path, file: &Path) -> String {
+    file.strip_prefix(folder)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+// 4g. decide where a file goes
+// override > (too long = skip) > filename keyword > confident analysis
+// overrides/guesses naming a category that no longer exists count as nothing
+fn assign(entry: &ManifestEntry, filename: &str, categories: &SfxCategories) -> (Option<String>, AssignmentSource) {
+    if entry.excluded {
+        return (None, AssignmentSource::Manifest);
+    }
+    if let Some(category) = entry.assigned.as_deref().and_then(|name| categories.get(name)) {
+        return (Some(category.name.clone()), AssignmentSource::Manifest);
+    }
+
+    // "background_drop_loop.wav" is a bed, no matter what the name says
+    let too_long = entry.features
+        .map(|f| f.duration > sfx_analysis::MAX_ONESHOT_SECONDS)
+        .unwrap_or(false);
+    if too_long {
+        return (None, AssignmentSource::Unassigned);
+    }
+
+    if let Some(category) = categories.match_filename(filename) {
+        return (Some(category.name.clone()), AssignmentSource::Filename);
+    }
+
+    match entry.suggested.and_then(|t| categories.get(t.name())) {
+        Some(category) if entry.confidence >= ANALYSIS_CONFIDENCE => (Some(category.name.clone()), AssignmentSource::Analysis),
+        _ => (None, AssignmentSource::Unassigned),
+    }
 }
 
 impl SfxLibrary {
     // 5a. scan a folder for sfx files
+    // new or changed files get analyzed (slow-ish, runs ffmpeg) and cached in the manifest
     pub fn load_from_folder(folder: impl AsRef<Path>) -> Self {
-        let mut sounds: HashMap<SfxType, Vec<PathBuf>> = HashMap::new();
+        let folder = folder.as_ref();
+        let categories = SfxCategories::load(Some(folder));
+        let mut sounds: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        let mut files = Vec::new();
         
         // init empty vecs
-        for sfx_type in SfxType::all() {
-            sounds.insert(*sfx_type, Vec::new());
+        for category in categories.all() {
+            sounds.insert(category.name.clone(), Vec::new());
         }
 
-        let folder = folder.as_ref();
         if !folder.exists() {
             log::warn!("sfx folder doesnt exist: {}", folder.display());
-            return Self { sounds };
+            return Self { categories, sounds, files };
         }
 
+        let mut manifest = SfxManifest::load(folder);
+        let mut seen = Vec::new();
+        let mut dirty = false;
+
         // walk the folder looking for audio files
         for entry in WalkDir::new(folder)
             .max_depth(2)
@@ -139,58 +303,253 @@ impl SfxLibrary {
                 continue;
             }
 
-            // try to match to sfx type
-            if let Some(filename) = path.file_stem() {
-                let filename = filename.to_string_lossy();
-                if let Some(sfx_type) = SfxType::from_filename(&filename) {
-                    log::info!("found sfx: {} -> {:?}", path.display(), sfx_type);
-                    sounds.get_mut(&sfx_type).unwrap().push(path.to_path_buf());
-                }
+            let key = manifest_key(folder, path);
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            let cached = manifest.files.entry(key.clone()).or_default();
+
+            // listen to anything we havent seen at this size before
+            if cached.size != size {
+                cached.size = size;
+                cached.features = match sfx_analysis::decode_mono(path) {
+                    Ok(samples) => sfx_analysis::analyze(&samples, sfx_analysis::ANALYSIS_RATE),
+                    Err(e) => {
+                        log::warn!("couldnt analyze {}: {}", path.display(), e);
+                        None
+                    }
+                };
+                let guess = cached.features.as_ref().and_then(sfx_analysis::classify);
+                cached.suggested = guess.map(|(t, _)| t);
+                cached.confidence = guess.map(|(_, c)| c).unwrap_or(0.0);
+                dirty = true;
+            }
+
+            let filename = path.file_stem().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+            let (category, source) = assign(cached, &filename, &categories);
+
+            if let Some(category) = &category {
+                log::info!("found sfx: {} -> {} ({:?})", path.display(), category, source);
+                sounds.get_mut(category).unwrap().push(path.to_path_buf());
             }
+            files.push(SfxFileInfo {
+                path: path.to_path_buf(),
+                category,
+                source,
+                suggested: cached.suggested,
+                confidence: cached.confidence,
+            });
+            seen.push(key);
         }
 
-        Self { sounds }
+        // forget analysis for deleted files, but keep user overrides around
+        let before = manifest.files.len();
+        manifest.files.retain(|key, entry| seen.contains(key) || entry.has_override());
+        dirty |= manifest.files.len() != before;
+
+        if dirty {
+            if let Err(e) = manifest.save(folder) {
+                log::warn!("couldnt save sfx manifest: {}", e);
+            }
+        }
+
+        Self { categories, sounds, files }
+    }
+
+    // 5e. everything the scan found, assigned or not
+    pub fn files(&self) -> &[SfxFileInfo] {
+        &self.files
+    }
+
+    // 5f. the categories this library was sorted with
+    pub fn categories(&self) -> &SfxCategories {
+        &self.categories
+    }
+
+    // 5b. get a random sound of a category
+    pub fn get_random(&self, category: &str) -> Option<&PathBuf> {
+        self.get_random_except(category, None)
     }
 
-    // 5b. get a random sound of a type
-    pub fn get_random(&self, sfx_type: SfxType) -> Option<&PathBuf> {
+    // same, but skips `except` when theres anything else to pick
+    pub fn get_random_except(&self, category: &str, except: Option<&PathBuf>) -> Option<&PathBuf> {
         use rand::seq::SliceRandom;
         let mut rng = rand::thread_rng();
-        self.sounds.get(&sfx_type)?.choose(&mut rng)
+        let sounds = self.sounds.get(category)?;
+        let fresh: Vec<&PathBuf> = sounds.iter().filter(|p| Some(*p) != except).collect();
+        if fresh.is_empty() {
+            sounds.choose(&mut rng)
+        } else {
+            fresh.choose(&mut rng).copied()
+        }
     }
 
-    // 5c. which types have sounds available
-    pub fn available_types(&self) -> Vec<SfxType> {
-        self.sounds
+    // 5c. which categories have sounds available
+    pub fn available_categories(&self) -> Vec<&SfxCategory> {
+        self.categories
+            .all()
             .iter()
-            .filter(|(_, v)| !v.is_empty())
-            .map(|(k, _)| *k)
+            .filter(|c| self.has_category(&c.name))
             .collect()
     }
 
-    // 5d. check if a type has sounds
-    pub fn has_type(&self, sfx_type: SfxType) -> bool {
+    // 5d. check if a category has sounds
+    pub fn has_category(&self, category: &str) -> bool {
         self.sounds
-            .get(&sfx_type)
+            .get(category)
             .map(|v| !v.is_empty())
             .unwrap_or(false)
     }
 }
 
 // 6a. sfx placement event
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SfxEvent {
-    pub sfx_type: SfxType,
+    pub category: String,
     pub timestamp: f64,  // seconds into video
+    pub gain_db: Option<f64>,  // None = the category's default level
+}
+
+// 6b. an event with its file picked, ready for the mixer
+// also what the ui gets to preview
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SfxPlacement {
+    pub timestamp: f64,
+    pub category: String,
+    pub path: PathBuf,
+    pub gain_db: f64,
+}
+
+impl SfxLibrary {
+    // 6c. pick a file for every event, events with no matching sounds get dropped
+    // never picks the same file twice in a row if the category has others
+    pub fn resolve(&self, events: &[SfxEvent]) -> Vec<SfxPlacement> {
+        let mut placements: Vec<SfxPlacement> = Vec::new();
+        for event in events {
+            let previous = placements.last().map(|p| &p.path);
+            let Some(path) = self.get_random_except(&event.category, previous) else {
+                continue;
+            };
+            let default_gain = self.categories.get(&event.category).map(|c| c.default_gain_db).unwrap_or(-10.0);
+            placements.push(SfxPlacement {
+                timestamp: event.timestamp,
+                category: event.category.clone(),
+                path: path.clone(),
+                gain_db: event.gain_db.unwrap_or(default_gain),
+            });
+        }
+        placements
+    }
+}
+
+// 7a. past this many events the ffmpeg graph gets silly
+// (one branch + adelay each), so the sfx track gets pre-rendered in rust instead
+pub const MAX_GRAPH_EVENTS: usize = 64;
+
+// 7b. placements grouped by file, in first-seen order
+// each unique file becomes one ffmpeg input that gets asplit to every use
+pub fn group_by_file(placements: &[SfxPlacement]) -> Vec<(PathBuf, Vec<&SfxPlacement>)> {
+    let mut groups: Vec<(PathBuf, Vec<&SfxPlacement>)> = Vec::new();
+    for placement in placements {
+        match groups.iter_mut().find(|(path, _)| *path == placement.path) {
+            Some((_, uses)) => uses.push(placement),
+            None => groups.push((placement.path.clone(), vec![placement])),
+        }
+    }
+    groups
+}
+
+// 7c. filter graph for the sfx bus, ends in [sfxmix]
+// first_input is the ffmpeg input index of the first group's file
+// normalize=0 so adding more sfx doesnt turn all of them down
+pub fn sfx_bus_graph(groups: &[(PathBuf, Vec<&SfxPlacement>)], first_input: usize) -> String {
+    let mut parts = Vec::new();
+    let mut branch = 0;
+
+    for (g, (_, uses)) in groups.iter().enumerate() {
+        let input = first_input + g;
+        let labels: Vec<String> = (0..uses.len()).map(|u| format!("[g{}u{}]", g, u)).collect();
+
+        if uses.len() > 1 {
+            parts.push(format!("[{}:a]asplit={}{}", input, uses.len(), labels.concat()));
+        } else {
+            parts.push(format!("[{}:a]anull{}", input, labels[0]));
+        }
+
+        for (label, placement) in labels.iter().zip(uses) {
+            let delay_ms = (placement.timestamp.max(0.0) * 1000.0).round() as u64;
+            parts.push(format!(
+                "{}aformat=sample_fmts=fltp:sample_rates=48000:channel_layouts=stereo,volume={:.1}dB,adelay=delays={}:all=1[sfx{}]",
+                label, placement.gain_db, delay_ms, branch
+            ));
+            branch += 1;
+        }
+    }
+
+    let inputs: String = (0..branch).map(|i| format!("[sfx{}]", i)).collect();
+    parts.push(format!("{}amix=inputs={}:duration=longest:normalize=0[sfxmix]", inputs, branch));
+    parts.join(";")
+}
+
+// 7d. mix decoded sounds into one interleaved stereo buffer
+// sounds are (start sample frame, linear gain, interleaved stereo samples)
+// anything running past the end gets cut off
+pub fn mix_sfx_track(frames: usize, sounds: &[(usize, f32, &[f32])]) -> Vec<f32> {
+    let mut track = vec![0.0f32; frames * 2];
+    for (start, gain, samples) in sounds {
+        let offset = start * 2;
+        if offset >= track.len() {
+            continue;
+        }
+        for (out, sample) in track[offset..].iter_mut().zip(samples.iter()) {
+            *out += sample * gain;
+        }
+    }
+    track
 }
 
-// ============================================
-// TODO: auto sfx placement
-// ============================================
-// could add automatic sfx based on:
-// - clip transitions (whoosh)
-// - audio peaks in user video (boom)
-// - random intervals (sparkle)
-//
-// for now sfx placement would be manual
-// or we add at every clip transition
+// 8a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(timestamp: f64, path: &str) -> SfxPlacement {
+        SfxPlacement { timestamp, category: "pop".to_string(), path: PathBuf::from(path), gain_db: -6.0 }
+    }
+
+    #[test]
+    fn test_assignment_order() {
+        let categories = SfxCategories::default();
+        let named = |name: &str| Some(name.to_string());
+
+        let mut entry = ManifestEntry { suggested: Some(SfxType::Whoosh), confidence: 0.9, ..ManifestEntry::default() };
+        assert_eq!(assign(&entry, "big_boom", &categories), (named("boom"), AssignmentSource::Filename));
+        assert_eq!(assign(&entry, "untitled_04", &categories), (named("whoosh"), AssignmentSource::Analysis));
+
+        entry.features = Some(AudioFeatures { duration: 45.0, attack: 0.5, decay: 1.0, peak_position: 0.5, centroid: 900.0, centroid_trend: 0.0, onsets: 20 });
+        assert_eq!(assign(&entry, "background_drop_loop", &categories), (None, AssignmentSource::Unassigned));
+
+        entry.assigned = named("faller");
+        assert_eq!(assign(&entry, "background_drop_loop", &categories), (named("faller"), AssignmentSource::Manifest));
+        entry.excluded = true;
+        assert_eq!(assign(&entry, "big_boom", &categories), (None, AssignmentSource::Manifest));
+    }
+
+    #[test]
+    fn test_repeated_files_share_an_input() {
+        let placements = vec![placement(1.0, "pop.wav"), placement(2.5, "boom.wav"), placement(4.0, "pop.wav")];
+        let groups = group_by_file(&placements);
+        assert_eq!(groups.len(), 2);
+
+        let graph = sfx_bus_graph(&groups, 1);
+        assert!(graph.starts_with("[1:a]asplit=2[g0u0][g0u1];"));
+        assert!(graph.contains("[2:a]anull[g1u0]"));
+        assert!(graph.contains("volume=-6.0dB,adelay=delays=4000:all=1[sfx1]"));
+        assert!(graph.ends_with("[sfx0][sfx1][sfx2]amix=inputs=3:duration=longest:normalize=0[sfxmix]"));
+    }
+
+    #[test]
+    fn test_mix_sfx_track() {
+        let click = [1.0f32, -1.0, 0.5, 0.5];
+        let track = mix_sfx_track(3, &[(0, 0.5, &click), (1, 1.0, &click), (5, 1.0, &click)]);
+        assert_eq!(track, vec![0.5, -0.5, 1.25, -0.75, 0.5, 0.5]);
+    }
+}
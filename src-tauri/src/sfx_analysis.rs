@@ -0,0 +1,244 @@
+// 1a. sfx_analysis.rs - figure out what an sfx file is by listening to it
+// 1b. decodes to mono pcm, measures envelope + spectrum, then scores each SfxType
+// 1c. plain rules, no model. good enough to sort a pack of unnamed one-shots
+// filenames still win when they match, this is for everything else
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::sfx::SfxType;
+
+// 2a. analysis runs at a low rate, plenty for centroid + envelope
+pub const ANALYSIS_RATE: u32 = 22050;
+
+// anything longer than this is a loop or a music bed, not a one-shot
+pub const MAX_ONESHOT_SECONDS: f64 = 6.0;
+
+const FRAME: usize = 512;          // ~23ms analysis window
+const MAX_SPECTRUM_FRAMES: usize = 48;
+
+// 2b. what we measure
+// times in seconds, positions are 0-1 through the sound, centroid in hz
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AudioFeatures {
+    pub duration: f64,
+    pub attack: f64,           // start to 90% of peak level
+    pub decay: f64,            // peak to -20dB below it
+    pub peak_position: f64,
+    pub centroid: f64,         // energy weighted average over the sound
+    pub centroid_trend: f64,   // last third minus first third, + = pitch goes up
+    pub onsets: usize,         // sharp jumps in level
+}
+
+// 3a. decode a file to mono f32 at ANALYSIS_RATE
+// sync on purpose, library scans happen off the async runtime
+pub fn decode_mono(path: &Path) -> std::io::Result<Vec<f32>> {
+    let output = std::process::Command::new("ffmpeg")
+        .args([
+            "-v", "error",
+            "-i", path.to_str().unwrap(),
+            "-vn",
+            "-ac", "1",
+            "-ar", &ANALYSIS_RATE.to_string(),
+            "-f", "f32le",
+            "-",
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(output.stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+// 4a. measure a decoded sound
+// None for silence or anything too short to say anything about
+pub fn analyze(samples: &[f32], rate: u32) -> Option<AudioFeatures> {
+    let hop = FRAME / 2;
+    if samples.len() < FRAME {
+        return None;
+    }
+
+    // rms envelope on half overlapping frames
+    let envelope: Vec<f32> = samples
+        .windows(FRAME)
+        .step_by(hop)
+        .map(|w| (w.iter().map(|s| s * s).sum::<f32>() / FRAME as f32).sqrt())
+        .collect();
+
+    let (peak_idx, peak) = envelope
+        .iter()
+        .enumerate()
+        .fold((0, 0.0f32), |best, (i, &v)| if v > best.1 { (i, v) } else { best });
+    if peak < 1e-4 {
+        return None;
+    }
+
+    let frame_secs = hop as f64 / rate as f64;
+    let duration = samples.len() as f64 / rate as f64;
+
+    let attack_idx = envelope.iter().position(|&v| v >= peak * 0.9).unwrap_or(peak_idx);
+    let decay_idx = envelope[peak_idx..]
+        .iter()
+        .position(|&v| v < peak * 0.1)
+        .map(|i| peak_idx + i)
+        .unwrap_or(envelope.len());
+
+    // onsets = level at least doubles frame to frame while being reasonably loud
+    let onsets = envelope
+        .windows(2)
+        .filter(|w| w[1] > w[0] * 2.0 && w[1] > peak * 0.2)
+        .count()
+        .max(1);
+
+    // centroid from a handful of windows spread over the audible part
+    let audible: Vec<usize> = (0..envelope.len()).filter(|&i| envelope[i] > peak * 0.05).collect();
+    let step = (audible.len() / MAX_SPECTRUM_FRAMES).max(1);
+    let centroids: Vec<(f64, f64)> = audible
+        .iter()
+        .step_by(step)
+        .filter_map(|&i| {
+            let start = i * hop;
+            samples.get(start..start + FRAME).map(|w| (spectral_centroid(w, rate), envelope[i] as f64))
+        })
+        .collect();
+
+    let weighted = |part: &[(f64, f64)]| -> f64 {
+        let total: f64 = part.iter().map(|(_, w)| w).sum();
+        if total <= 0.0 {
+            0.0
+        } else {
+            part.iter().map(|(c, w)| c * w).sum::<f64>() / total
+        }
+    };
+
+    let third = (centroids.len() / 3).max(1);
+    let centroid_trend = if centroids.len() >= 3 {
+        weighted(&centroids[centroids.len() - third..]) - weighted(&centroids[..third])
+    } else {
+        0.0
+    };
+
+    Some(AudioFeatures {
+        duration,
+        attack: attack_idx as f64 * frame_secs,
+        decay: (decay_idx - peak_idx) as f64 * frame_secs,
+        peak_position: peak_idx as f64 / envelope.len().max(1) as f64,
+        centroid: weighted(&centroids),
+        centroid_trend,
+        onsets,
+    })
+}
+
+// 4b. spectral centroid of one hann windowed frame
+// plain dft, only a few dozen frames per file so no need for an fft crate
+fn spectral_centroid(frame: &[f32], rate: u32) -> f64 {
+    let n = frame.len();
+    let windowed: Vec<f64> = frame
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| s as f64 * (0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / n as f64).cos()))
+        .collect();
+
+    let mut weighted = 0.0;
+    let mut total = 0.0;
+    for k in 1..n / 2 {
+        let (mut re, mut im) = (0.0, 0.0);
+        let step = 2.0 * std::f64::consts::PI * k as f64 / n as f64;
+        for (i, s) in windowed.iter().enumerate() {
+            let angle = step * i as f64;
+            re += s * angle.cos();
+            im -= s * angle.sin();
+        }
+        let magnitude = (re * re + im * im).sqrt();
+        weighted += magnitude * k as f64 * rate as f64 / n as f64;
+        total += magnitude;
+    }
+
+    if total > 0.0 { weighted / total } else { 0.0 }
+}
+
+// 5a. score every type against the features, best one wins
+// each rule is a list of checks, score is the fraction that pass
+// returns None for long loops/beds, otherwise the best type and its score
+pub fn classify(features: &AudioFeatures) -> Option<(SfxType, f32)> {
+    let f = features;
+    if f.duration > MAX_ONESHOT_SECONDS {
+        return None;
+    }
+
+    let sharp = f.attack < 0.03;
+    let score = |checks: &[bool]| checks.iter().filter(|c| **c).count() as f32 / checks.len() as f32;
+
+    let scores = [
+        (SfxType::Click, score(&[f.duration < 0.2, sharp, f.centroid > 2000.0, f.onsets <= 2])),
+        (SfxType::Pop, score(&[f.duration < 0.5, sharp, (500.0..3000.0).contains(&f.centroid), f.decay < 0.2])),
+        (SfxType::Thud, score(&[f.duration < 1.2, sharp, f.centroid < 600.0, f.decay < 0.5])),
+        (SfxType::Boom, score(&[f.duration >= 0.8, sharp, f.centroid < 500.0, f.decay >= 0.5])),
+        (SfxType::Riser, score(&[f.duration >= 1.0, f.peak_position > 0.6, f.attack > f.duration * 0.4, f.centroid_trend > 150.0])),
+        (SfxType::Faller, score(&[f.duration >= 0.5, f.peak_position < 0.35, f.centroid_trend < -150.0, f.decay > 0.3])),
+        (SfxType::Whoosh, score(&[(0.2..2.0).contains(&f.duration), (0.2..0.8).contains(&f.peak_position), !sharp, f.centroid > 1200.0])),
+        (SfxType::Glitch, score(&[f.duration < 2.0, f.onsets >= 4, f.centroid > 1000.0])),
+        (SfxType::Ching, score(&[sharp, f.centroid > 2500.0, (0.2..2.5).contains(&f.decay), f.onsets <= 3])),
+        (SfxType::Sparkle, score(&[f.duration >= 0.5, f.centroid > 3500.0, !sharp || f.onsets >= 3])),
+    ];
+
+    scores
+        .iter()
+        .copied()
+        .fold(None, |best: Option<(SfxType, f32)>, (t, s)| match best {
+            Some((_, b)) if b >= s => best,
+            _ => Some((t, s)),
+        })
+}
+
+// 6a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = ANALYSIS_RATE;
+
+    fn tone(seconds: f64, freq: impl Fn(f64) -> f64, level: impl Fn(f64) -> f64) -> Vec<f32> {
+        let n = (seconds * RATE as f64) as usize;
+        let mut phase = 0.0;
+        (0..n)
+            .map(|i| {
+                let t = i as f64 / RATE as f64;
+                phase += 2.0 * std::f64::consts::PI * freq(t) / RATE as f64;
+                (phase.sin() * level(t)) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_low_hit_with_tail_is_a_boom() {
+        let samples = tone(2.0, |_| 60.0, |t| (-t * 3.0).exp());
+        let features = analyze(&samples, RATE).unwrap();
+        assert!(features.attack < 0.03);
+        assert!(features.centroid < 500.0, "{:?}", features);
+        assert_eq!(classify(&features).unwrap().0, SfxType::Boom);
+    }
+
+    #[test]
+    fn test_rising_sweep_is_a_riser() {
+        let samples = tone(3.0, |t| 300.0 + t * 1500.0, |t| t / 3.0);
+        let features = analyze(&samples, RATE).unwrap();
+        assert!(features.centroid_trend > 1000.0, "{:?}", features);
+        assert_eq!(classify(&features).unwrap().0, SfxType::Riser);
+    }
+
+    #[test]
+    fn test_silence_and_long_beds() {
+        assert!(analyze(&vec![0.0; RATE as usize], RATE).is_none());
+
+        let bed = AudioFeatures { duration: 30.0, attack: 1.0, decay: 1.0, peak_position: 0.5, centroid: 800.0, centroid_trend: 0.0, onsets: 12 };
+        assert!(classify(&bed).is_none());
+    }
+}
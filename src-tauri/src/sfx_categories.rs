@@ -0,0 +1,203 @@
+// 1a. sfx_categories.rs - data driven sfx categories
+// 1b. the 10 built-in SfxTypes are just the default set now
+// 1c. users add their own (typewriter, shutter, notification...) in sfx_categories.json
+// either in the app data dir (every library) or in the sfx folder itself (that library only)
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::sfx::SfxType;
+
+pub const CATEGORIES_FILE: &str = "sfx_categories.json";
+
+// 2a. what makes the auto placer drop a sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SfxTrigger {
+    Transition,  // on the cut between broll clips
+    Emphasis,    // on a loud/stressed moment in the voice
+    Interval,    // every so often to keep things moving
+    Manual,      // never auto placed, only from cue sheets
+}
+
+// 2b. placement rules per category, the auto placer reads these
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlacementRules {
+    pub triggers: Vec<SfxTrigger>,
+    pub offset: f64,          // seconds relative to the trigger, negative = lead in (risers)
+    pub min_spacing: f64,     // seconds between two sounds of this category
+    pub max_per_minute: f64,
+    pub over_speech: bool,    // ok to play while someone is talking
+}
+
+impl Default for PlacementRules {
+    fn default() -> Self {
+        Self {
+            triggers: vec![SfxTrigger::Manual],
+            offset: 0.0,
+            min_spacing: 4.0,
+            max_per_minute: 6.0,
+            over_speech: false,
+        }
+    }
+}
+
+// 2c. one category
+// keywords are matched case-insensitively against the file name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SfxCategory {
+    pub name: String,
+    pub description: String,
+    pub keywords: Vec<String>,
+    pub default_gain_db: f64,
+    pub placement: PlacementRules,
+    #[serde(skip_deserializing)]
+    pub builtin: bool,
+}
+
+impl Default for SfxCategory {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: String::new(),
+            keywords: Vec::new(),
+            default_gain_db: -10.0,
+            placement: PlacementRules::default(),
+            builtin: false,
+        }
+    }
+}
+
+impl SfxCategory {
+    // 3a. the built-in category for an SfxType
+    pub fn builtin(sfx_type: SfxType) -> Self {
+        Self {
+            name: sfx_type.name().to_string(),
+            description: sfx_type.description().to_string(),
+            keywords: sfx_type.keywords().iter().map(|k| k.to_string()).collect(),
+            default_gain_db: sfx_type.default_gain_db(),
+            placement: sfx_type.default_placement(),
+            builtin: true,
+        }
+    }
+
+    pub fn matches(&self, filename: &str) -> bool {
+        let lower = filename.to_lowercase();
+        self.keywords.iter().any(|k| !k.is_empty() && lower.contains(&k.to_lowercase()))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct CategoriesFile {
+    #[serde(default)]
+    categories: Vec<SfxCategory>,
+}
+
+// 4a. the full category list, in match order
+// user categories come first so "camera_click" lands in shutter before the built-in click
+#[derive(Debug, Clone)]
+pub struct SfxCategories {
+    list: Vec<SfxCategory>,
+}
+
+impl Default for SfxCategories {
+    fn default() -> Self {
+        Self {
+            list: SfxType::all().iter().map(|t| SfxCategory::builtin(*t)).collect(),
+        }
+    }
+}
+
+impl SfxCategories {
+    // 4b. built-ins + the global config + the folder's config
+    // a user category with a built-in's name replaces it (retuned keywords/levels)
+    // broken config files get logged and skipped, never fatal
+    pub fn load(folder: Option<&Path>) -> Self {
+        let mut paths: Vec<PathBuf> = global_config_path().into_iter().collect();
+        if let Some(folder) = folder {
+            paths.push(folder.join(CATEGORIES_FILE));
+        }
+
+        let mut custom: Vec<SfxCategory> = Vec::new();
+        for path in paths.iter().filter(|p| p.exists()) {
+            let parsed = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| serde_json::from_str::<CategoriesFile>(&text).map_err(|e| e.to_string()));
+
+            match parsed {
+                Ok(file) => {
+                    for category in file.categories.into_iter().filter(|c| !c.name.trim().is_empty()) {
+                        custom.retain(|c| !c.name.eq_ignore_ascii_case(&category.name));
+                        custom.push(category);
+                    }
+                }
+                Err(e) => log::warn!("skipping sfx categories file {}: {}", path.display(), e),
+            }
+        }
+
+        Self::with_custom(custom)
+    }
+
+    fn with_custom(custom: Vec<SfxCategory>) -> Self {
+        let mut list = custom;
+        for builtin in Self::default().list {
+            match list.iter_mut().find(|c| c.name.eq_ignore_ascii_case(&builtin.name)) {
+                // overriding a built-in keeps it flagged so the ui can say so
+                Some(existing) => existing.builtin = true,
+                None => list.push(builtin),
+            }
+        }
+        Self { list }
+    }
+
+    pub fn all(&self) -> &[SfxCategory] {
+        &self.list
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SfxCategory> {
+        self.list.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    // 4c. first category whose keywords hit the file name
+    pub fn match_filename(&self, filename: &str) -> Option<&SfxCategory> {
+        self.list.iter().find(|c| c.matches(filename))
+    }
+}
+
+// app wide config, next to the downloaded binaries and models
+fn global_config_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("godz").join(CATEGORIES_FILE))
+}
+
+// 5a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtins_match_like_before() {
+        let categories = SfxCategories::default();
+        assert_eq!(categories.all().len(), 10);
+        assert_eq!(categories.match_filename("Cash_Register_01").unwrap().name, "ching");
+        assert_eq!(categories.match_filename("big_bass_hit").unwrap().name, "boom");
+        assert!(categories.match_filename("typewriter").is_none());
+    }
+
+    #[test]
+    fn test_custom_categories_go_first() {
+        let file: CategoriesFile = serde_json::from_str(r#"{"categories": [
+            {"name": "shutter", "keywords": ["shutter", "camera"], "default_gain_db": -7},
+            {"name": "click", "keywords": ["click", "mouse"], "default_gain_db": -3}
+        ]}"#).unwrap();
+        let categories = SfxCategories::with_custom(file.categories);
+
+        assert_eq!(categories.all().len(), 11);
+        assert_eq!(categories.match_filename("camera_click").unwrap().name, "shutter");
+
+        let click = categories.get("Click").unwrap();
+        assert!(click.builtin);
+        assert_eq!((click.default_gain_db, click.matches("mouse_down")), (-3.0, true));
+        assert_eq!(click.placement.triggers, vec![SfxTrigger::Manual]);
+    }
+}
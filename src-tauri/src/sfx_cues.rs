@@ -0,0 +1,357 @@
+// 1a. sfx_cues.rs - hand made sfx cue sheets
+// 1b. csv or json list of (timestamp, category or file, gain) a job can use
+// instead of auto placement
+// 1c. auto placed sfx export to the same format so you can tweak and re-run
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::sfx::{SfxEvent, SfxLibrary, SfxPlacement};
+use crate::sfx_categories::SfxCategories;
+
+// 2a. errors - parse errors say which line, validation lists every bad cue at once
+#[derive(Error, Debug)]
+pub enum CueError {
+    #[error("cue sheet parse error on line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("cue sheet json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("bad cue sheet: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+pub type CueResult<T> = Result<T, CueError>;
+
+// 2b. one cue - a category gets a random file from the library, a file plays as is
+// when both are set the file wins and the category is just a label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SfxCue {
+    pub timestamp: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain_db: Option<f64>,
+}
+
+// json is either {"cues": [...]} or just the array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CueFile {
+    Wrapped { cues: Vec<SfxCue> },
+    Bare(Vec<SfxCue>),
+}
+
+#[derive(Serialize)]
+struct CueFileOut<'a> {
+    cues: &'a [SfxCue],
+}
+
+const CSV_HEADER: &str = "timestamp,category,file,gain_db";
+
+// 3a. load a cue sheet, format from the extension
+// relative file paths are relative to the cue sheet
+pub fn load_cues(path: &Path) -> CueResult<Vec<SfxCue>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut cues = if is_json(path) { parse_json(&text)? } else { parse_csv(&text)? };
+
+    let base = path.parent().unwrap_or(Path::new("."));
+    for cue in cues.iter_mut() {
+        if let Some(file) = cue.file.as_mut().filter(|f| f.is_relative()) {
+            *file = base.join(&*file);
+        }
+    }
+    Ok(cues)
+}
+
+// 3b. write a cue sheet, same extension rule
+pub fn save_cues(path: &Path, cues: &[SfxCue]) -> CueResult<()> {
+    let text = if is_json(path) {
+        serde_json::to_string_pretty(&CueFileOut { cues })?
+    } else {
+        to_csv(cues)
+    };
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+// 4a. csv parser
+// header row names the columns (any order, unknown ones ignored), # lines are comments
+// timestamps can be seconds or mm:ss / hh:mm:ss like an editor timeline shows
+pub fn parse_csv(text: &str) -> CueResult<Vec<SfxCue>> {
+    let mut rows = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let Some((header_line, header)) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<String> = split_csv_row(header, header_line)?
+        .into_iter()
+        .map(|c| c.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+
+    let time_col = column(&["timestamp", "time", "start"]).ok_or_else(|| CueError::Parse {
+        line: header_line,
+        message: format!("header needs a timestamp column, expected '{}'", CSV_HEADER),
+    })?;
+    let category_col = column(&["category", "type", "sfx"]);
+    let file_col = column(&["file", "path"]);
+    let gain_col = column(&["gain_db", "gain"]);
+
+    let mut cues = Vec::new();
+    for (line, row) in rows {
+        let fields = split_csv_row(row, line)?;
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+        };
+
+        let timestamp = field(Some(time_col))
+            .ok_or_else(|| parse_error(line, "missing timestamp".to_string()))
+            .and_then(|t| parse_timestamp(t).ok_or_else(|| parse_error(line, format!("bad timestamp '{}'", t))))?;
+        let gain_db = match field(gain_col) {
+            Some(g) => Some(g.trim_end_matches("dB").trim().parse::<f64>()
+                .map_err(|_| parse_error(line, format!("bad gain '{}'", g)))?),
+            None => None,
+        };
+
+        cues.push(SfxCue {
+            timestamp,
+            category: field(category_col).map(|c| c.to_string()),
+            file: field(file_col).map(PathBuf::from),
+            gain_db,
+        });
+    }
+
+    Ok(cues)
+}
+
+// 4b. json parser
+pub fn parse_json(text: &str) -> CueResult<Vec<SfxCue>> {
+    Ok(match serde_json::from_str::<CueFile>(text)? {
+        CueFile::Wrapped { cues } | CueFile::Bare(cues) => cues,
+    })
+}
+
+// 4c. "12.5", "1:02.5", "00:01:02.500"
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in text.trim().split(':') {
+        let value: f64 = part.trim().parse().ok()?;
+        seconds = seconds * 60.0 + value;
+    }
+    seconds.is_finite().then_some(seconds)
+}
+
+// split one row, double quotes for fields with commas ("" is a literal quote)
+fn split_csv_row(row: &str, line: usize) -> CueResult<Vec<String>> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+
+    if quoted {
+        return Err(parse_error(line, "unclosed quote".to_string()));
+    }
+    Ok(fields)
+}
+
+fn parse_error(line: usize, message: String) -> CueError {
+    CueError::Parse { line, message }
+}
+
+// 4d. csv writer, timestamps in plain seconds so spreadsheets can sort them
+pub fn to_csv(cues: &[SfxCue]) -> String {
+    let quote = |s: &str| {
+        if s.contains([',', '"']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+
+    let mut out = format!("{}\n", CSV_HEADER);
+    for cue in cues {
+        out.push_str(&format!(
+            "{:.3},{},{},{}\n",
+            cue.timestamp,
+            quote(cue.category.as_deref().unwrap_or("")),
+            quote(&cue.file.as_ref().map(|f| f.to_string_lossy().to_string()).unwrap_or_default()),
+            cue.gain_db.map(|g| format!("{:.1}", g)).unwrap_or_default(),
+        ));
+    }
+    out
+}
+
+// 5a. export placed sfx (auto or previewed) as cues
+// files + gains are written out so a re-run sounds exactly the same until edited
+pub fn from_placements(placements: &[SfxPlacement]) -> Vec<SfxCue> {
+    placements
+        .iter()
+        .map(|p| SfxCue {
+            timestamp: p.timestamp,
+            category: Some(p.category.clone()),
+            file: Some(p.path.clone()),
+            gain_db: Some(p.gain_db),
+        })
+        .collect()
+}
+
+// 5b. check a sheet against the user video and the categories
+// every problem gets reported, not just the first one
+pub fn validate(
+    cues: &[SfxCue],
+    duration: f64,
+    categories: &SfxCategories,
+    library: Option<&SfxLibrary>,
+) -> CueResult<()> {
+    let mut problems = Vec::new();
+
+    for (i, cue) in cues.iter().enumerate() {
+        let n = i + 1;
+        if cue.timestamp < 0.0 || cue.timestamp >= duration {
+            problems.push(format!(
+                "cue {} at {:.2}s is outside the video (0 - {:.2}s)",
+                n, cue.timestamp, duration
+            ));
+        }
+
+        match (&cue.file, &cue.category) {
+            (Some(file), _) if !file.exists() => {
+                problems.push(format!("cue {}: file not found: {}", n, file.display()));
+            }
+            (Some(_), _) => {}
+            (None, Some(category)) if categories.get(category).is_none() => {
+                problems.push(format!("cue {}: theres no sfx category called '{}'", n, category));
+            }
+            (None, Some(category)) if !library.map(|l| l.has_category(category)).unwrap_or(false) => {
+                problems.push(format!("cue {}: no '{}' sounds in the sfx folder", n, category));
+            }
+            (None, Some(_)) => {}
+            (None, None) => problems.push(format!("cue {} needs a category or a file", n)),
+        }
+
+        if cue.gain_db.map(|g| !g.is_finite() || g > 24.0).unwrap_or(false) {
+            problems.push(format!("cue {}: gain {:?}dB is way too hot", n, cue.gain_db.unwrap_or_default()));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(CueError::Invalid(problems))
+    }
+}
+
+// 5c. turn a (validated) sheet into placements for the mixer
+// explicit files skip the library, categories pick a file like auto placement does
+pub fn resolve(cues: &[SfxCue], categories: &SfxCategories, library: Option<&SfxLibrary>) -> Vec<SfxPlacement> {
+    let mut placements: Vec<SfxPlacement> = cues
+        .iter()
+        .filter_map(|cue| {
+            let file = cue.file.clone()?;
+            let category = cue.category.clone().unwrap_or_else(|| "manual".to_string());
+            let default_gain = categories.get(&category).map(|c| c.default_gain_db).unwrap_or(-10.0);
+            Some(SfxPlacement {
+                timestamp: cue.timestamp,
+                category,
+                path: file,
+                gain_db: cue.gain_db.unwrap_or(default_gain),
+            })
+        })
+        .collect();
+
+    let events: Vec<SfxEvent> = cues
+        .iter()
+        .filter(|cue| cue.file.is_none())
+        .filter_map(|cue| {
+            Some(SfxEvent {
+                category: cue.category.clone()?,
+                timestamp: cue.timestamp,
+                gain_db: cue.gain_db,
+            })
+        })
+        .collect();
+    if let Some(library) = library {
+        placements.extend(library.resolve(&events));
+    }
+
+    placements.sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap_or(std::cmp::Ordering::Equal));
+    placements
+}
+
+// 6a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_round_trip() {
+        let text = "# intro hits\nTime,Gain,Category,File\n0:01.5,-6dB,whoosh,\n12,,,\"sounds/a, b.wav\"\n";
+        let cues = parse_csv(text).unwrap();
+        assert_eq!(cues, vec![
+            SfxCue { timestamp: 1.5, category: Some("whoosh".into()), file: None, gain_db: Some(-6.0) },
+            SfxCue { timestamp: 12.0, category: None, file: Some("sounds/a, b.wav".into()), gain_db: None },
+        ]);
+        assert_eq!(parse_csv(&to_csv(&cues)).unwrap(), cues);
+
+        match parse_csv("timestamp,category\n1,pop\nabc,pop\n") {
+            Err(CueError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_and_validation() {
+        let bare = parse_json(r#"[{"timestamp": 2, "category": "pop"}, {"timestamp": 90, "category": "nope"}]"#).unwrap();
+        let wrapped = parse_json(r#"{"cues": [{"timestamp": 2, "category": "pop"}]}"#).unwrap();
+        assert_eq!(bare[0], wrapped[0]);
+
+        let categories = SfxCategories::default();
+        match validate(&bare, 60.0, &categories, None) {
+            Err(CueError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 3);
+                assert!(problems[0].contains("'pop' sounds"));
+                assert!(problems[1].contains("outside the video"));
+                assert!(problems[2].contains("'nope'"));
+            }
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_export_then_resolve_is_exact() {
+        let placed = vec![SfxPlacement { timestamp: 3.0, category: "boom".into(), path: "/sfx/boom.wav".into(), gain_db: -4.5 }];
+        let cues = from_placements(&placed);
+        assert_eq!(resolve(&cues, &SfxCategories::default(), None), placed);
+    }
+}
@@ -0,0 +1,277 @@
+// 1a. sfx_placement.rs - automatic sfx placement
+// 1b. finds moments (broll cuts, loud/stressed words, quiet stretches)
+// 1c. then hands them to categories by their placement rules, with density + spacing limits
+// output is plain SfxEvents, same as a hand made cue list
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::processor::{Processor, ProcessResult};
+use crate::sfx::SfxEvent;
+use crate::sfx_categories::{SfxCategory, SfxTrigger};
+
+// 2a. job level knobs on top of the per-category rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoPlacement {
+    pub enabled: bool,
+    pub density: f64,            // scales every per-minute limit, 0.5 = half as busy
+    pub max_per_minute: f64,     // across all categories
+    pub min_spacing: f64,        // seconds between any two sfx
+    pub allow_over_speech: bool, // ignore the categories' over_speech = false
+    pub interval: f64,           // seconds between interval moments
+}
+
+impl Default for AutoPlacement {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            density: 1.0,
+            max_per_minute: 10.0,
+            min_spacing: 1.0,
+            allow_over_speech: false,
+            interval: 12.0,
+        }
+    }
+}
+
+// 2b. what we know about the voice track
+#[derive(Debug, Clone, Default)]
+pub struct VoiceAnalysis {
+    pub speech: Vec<(f64, f64)>,    // (start, end) of talking
+    pub emphasis: Vec<(f64, f64)>,  // (time, strength 0-1) of punchy moments
+}
+
+impl VoiceAnalysis {
+    pub fn is_speech(&self, t: f64) -> bool {
+        self.speech.iter().any(|(start, end)| t >= *start && t < *end)
+    }
+}
+
+const VOICE_RATE: u32 = 16000;
+const FRAME_SECS: f64 = 0.05;
+
+// 3a. decode the user audio and analyze it
+pub async fn analyze_voice_file(processor: &Processor, path: &Path) -> ProcessResult<VoiceAnalysis> {
+    let stereo = processor.decode_audio(path, VOICE_RATE).await?;
+    let mono: Vec<f32> = stereo.chunks_exact(2).map(|c| (c[0] + c[1]) * 0.5).collect();
+    Ok(analyze_voice(&mono, VOICE_RATE))
+}
+
+// 3b. energy based speech + emphasis detection
+// speech = frames a good bit over the noise floor, with small gaps bridged
+// emphasis = local loudness peaks that stick out of the surrounding 3 seconds
+pub fn analyze_voice(samples: &[f32], rate: u32) -> VoiceAnalysis {
+    let frame = ((rate as f64 * FRAME_SECS) as usize).max(1);
+    let levels: Vec<f64> = samples
+        .chunks(frame)
+        .map(|c| {
+            let rms = (c.iter().map(|s| s * s).sum::<f32>() / c.len() as f32).sqrt();
+            20.0 * (rms.max(1e-6) as f64).log10()
+        })
+        .collect();
+
+    if levels.is_empty() {
+        return VoiceAnalysis::default();
+    }
+
+    let mut sorted = levels.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let floor = sorted[sorted.len() / 10];
+    let threshold = (floor + 10.0).max(-50.0);
+
+    // speech segments, gaps under 0.3s bridged, blips under 0.2s dropped
+    let mut speech: Vec<(f64, f64)> = Vec::new();
+    let mut start: Option<usize> = None;
+    for (i, level) in levels.iter().enumerate() {
+        match (start, *level > threshold) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                speech.push((s as f64 * FRAME_SECS, i as f64 * FRAME_SECS));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        speech.push((s as f64 * FRAME_SECS, levels.len() as f64 * FRAME_SECS));
+    }
+
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for segment in speech {
+        match merged.last_mut() {
+            Some(last) if segment.0 - last.1 < 0.3 => last.1 = segment.1,
+            _ => merged.push(segment),
+        }
+    }
+    merged.retain(|(s, e)| e - s >= 0.2);
+
+    // emphasis: >= 6dB over the surrounding speech and the loudest frame within 0.5s
+    // averaging only voiced frames keeps the start of every sentence from counting
+    let context = (1.5 / FRAME_SECS) as usize;
+    let peak_radius = (0.5 / FRAME_SECS) as usize;
+    let emphasis = (0..levels.len())
+        .filter_map(|i| {
+            let level = levels[i];
+            if level <= threshold {
+                return None;
+            }
+            let voiced: Vec<f64> = levels[i.saturating_sub(context)..(i + context + 1).min(levels.len())]
+                .iter()
+                .copied()
+                .filter(|l| *l > threshold)
+                .collect();
+            let average = voiced.iter().sum::<f64>() / voiced.len() as f64;
+
+            // ties go to the first frame so a flat peak only counts once
+            let before = &levels[i.saturating_sub(peak_radius)..i];
+            let after = &levels[i + 1..(i + peak_radius + 1).min(levels.len())];
+            let is_peak = before.iter().all(|&l| l < level) && after.iter().all(|&l| l <= level);
+
+            let excess = level - average;
+            if is_peak && excess >= 6.0 {
+                Some((i as f64 * FRAME_SECS, (excess / 18.0).min(1.0)))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    VoiceAnalysis { speech: merged, emphasis }
+}
+
+// 4a. evenly spaced cut estimates, for previews made before the broll gets cut
+pub fn estimate_boundaries(duration: f64, clip_length: f64) -> Vec<f64> {
+    let step = clip_length.max(0.5);
+    (1..)
+        .map(|i| i as f64 * step)
+        .take_while(|t| *t < duration)
+        .collect()
+}
+
+// 5a. place events
+// moments are handled transitions first, then emphasis (strongest first), then intervals
+// each moment goes to the least used category that wants that trigger and passes the rules
+pub fn plan_events(
+    categories: &[&SfxCategory],
+    boundaries: &[f64],
+    voice: &VoiceAnalysis,
+    duration: f64,
+    config: &AutoPlacement,
+) -> Vec<SfxEvent> {
+    if !config.enabled || duration <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut moments: Vec<(SfxTrigger, f64)> = boundaries
+        .iter()
+        .filter(|t| **t > 0.0 && **t < duration)
+        .map(|t| (SfxTrigger::Transition, *t))
+        .collect();
+
+    let mut emphasis = voice.emphasis.clone();
+    emphasis.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    moments.extend(emphasis.iter().map(|(t, _)| (SfxTrigger::Emphasis, *t)));
+
+    if config.interval > 0.0 {
+        moments.extend(estimate_boundaries(duration, config.interval).into_iter().map(|t| (SfxTrigger::Interval, t)));
+    }
+
+    let mut placed: Vec<(usize, f64)> = Vec::new();  // (category index, time)
+    let mut uses = vec![0usize; categories.len()];
+
+    for (trigger, moment) in moments {
+        let mut candidates: Vec<usize> = (0..categories.len())
+            .filter(|&c| categories[c].placement.triggers.contains(&trigger))
+            .collect();
+        // variety - least used first, ties keep config order
+        candidates.sort_by_key(|&c| uses[c]);
+
+        for c in candidates {
+            let rules = &categories[c].placement;
+            let time = moment + rules.offset;
+
+            let fits = time >= 0.0
+                && time < duration - 0.2
+                && (rules.over_speech || config.allow_over_speech || !voice.is_speech(time))
+                && placed.iter().all(|(_, t)| (t - time).abs() >= config.min_spacing)
+                && placed.iter().filter(|(pc, _)| *pc == c).all(|(_, t)| (t - time).abs() >= rules.min_spacing)
+                && within_rate(&placed, None, time, config.max_per_minute * config.density)
+                && within_rate(&placed, Some(c), time, rules.max_per_minute * config.density);
+
+            if fits {
+                placed.push((c, time));
+                uses[c] += 1;
+                break;
+            }
+        }
+    }
+
+    placed.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    placed
+        .into_iter()
+        .map(|(c, time)| SfxEvent {
+            category: categories[c].name.clone(),
+            timestamp: time,
+            gain_db: None,
+        })
+        .collect()
+}
+
+// per-minute cap checked over a sliding minute centered on the new event
+fn within_rate(placed: &[(usize, f64)], category: Option<usize>, time: f64, per_minute: f64) -> bool {
+    let limit = per_minute.max(1.0).floor() as usize;
+    let nearby = placed
+        .iter()
+        .filter(|(c, t)| category.map(|want| want == *c).unwrap_or(true) && (t - time).abs() < 30.0)
+        .count();
+    nearby < limit
+}
+
+// 6a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sfx::SfxType;
+
+    fn category(sfx_type: SfxType) -> SfxCategory {
+        SfxCategory::builtin(sfx_type)
+    }
+
+    #[test]
+    fn test_analyze_voice_finds_speech_and_a_shout() {
+        let rate = 1000;
+        let mut samples = vec![0.001f32; rate * 10];
+        // talking from 2s to 6s, with a shout at 4s
+        for (i, s) in samples.iter_mut().enumerate().take(rate * 6).skip(rate * 2) {
+            *s = if (4000..4100).contains(&i) { 0.8 } else { 0.1 } * if i % 2 == 0 { 1.0 } else { -1.0 };
+        }
+
+        let voice = analyze_voice(&samples, rate as u32);
+        assert_eq!(voice.speech, vec![(2.0, 6.0)]);
+        assert!(voice.is_speech(3.0) && !voice.is_speech(7.0));
+        assert_eq!(voice.emphasis.len(), 1);
+        assert!((voice.emphasis[0].0 - 4.0).abs() < 0.11);
+    }
+
+    #[test]
+    fn test_rules_and_variety() {
+        let whoosh = category(SfxType::Whoosh);
+        let glitch = category(SfxType::Glitch);
+        let pop = category(SfxType::Pop);
+        let categories = vec![&whoosh, &glitch, &pop];
+
+        let voice = VoiceAnalysis {
+            speech: vec![(0.0, 20.0)],
+            emphasis: vec![(5.0, 0.9)],
+        };
+        let config = AutoPlacement { interval: 0.0, ..AutoPlacement::default() };
+        let events = plan_events(&categories, &[3.0, 3.5, 6.0, 9.0, 20.0], &voice, 20.0, &config);
+
+        // 3.5 is too close to 3.0, the pop would land on speech, cuts alternate whoosh/glitch
+        let names: Vec<(&str, f64)> = events.iter().map(|e| (e.category.as_str(), (e.timestamp * 100.0).round() / 100.0)).collect();
+        assert_eq!(names, vec![("whoosh", 2.85), ("glitch", 6.0), ("whoosh", 8.85)]);
+
+        let quiet = AutoPlacement { enabled: false, ..config };
+        assert!(plan_events(&categories, &[3.0], &voice, 20.0, &quiet).is_empty());
+    }
+}
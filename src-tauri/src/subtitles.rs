@@ -0,0 +1,228 @@
+// 1a. subtitles.rs - load existing srt/vtt files
+// 1b. for people who already have corrected subs from their editor
+// 1c. parses into the same cues the captions module uses so burn in is shared
+
+use std::path::Path;
+use thiserror::Error;
+use regex::Regex;
+use crate::captions::CaptionCue;
+
+// 2a. errors - parse errors always say which line broke
+#[derive(Error, Debug)]
+pub enum SubtitleError {
+    #[error("subtitle parse error on line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+pub type SubtitleResult<T> = Result<T, SubtitleError>;
+
+// 3a. load a subtitle file from disk
+// format comes from the extension, falls back to sniffing the WEBVTT header
+pub fn load_subtitles(path: &Path) -> SubtitleResult<Vec<CaptionCue>> {
+    let text = std::fs::read_to_string(path)?;
+    let is_vtt = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case("vtt"))
+        .unwrap_or(false)
+        || text.trim_start_matches('\u{feff}').starts_with("WEBVTT");
+
+    if is_vtt {
+        parse_vtt(&text)
+    } else {
+        parse_srt(&text)
+    }
+}
+
+// 4a. srt parser
+// blocks of: index, "start --> end", text lines, blank line
+// the index is optional cuz plenty of tools write broken ones
+pub fn parse_srt(text: &str) -> SubtitleResult<Vec<CaptionCue>> {
+    let lines = split_lines(text);
+    let mut cues = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+
+        // skip the counter if its there
+        if !lines[i].contains("-->") {
+            if lines[i].trim().parse::<u64>().is_err() {
+                return Err(parse_error(i, format!("expected a cue number or timing, got '{}'", lines[i].trim())));
+            }
+            i += 1;
+            if i >= lines.len() {
+                return Err(parse_error(i - 1, "cue number with no timing after it".to_string()));
+            }
+        }
+
+        let (start, end) = parse_timing(lines[i], i)?;
+        i += 1;
+
+        let (cue_text, next) = collect_text(&lines, i);
+        i = next;
+
+        cues.push(CaptionCue { start, end, text: cue_text, words: Vec::new() });
+    }
+
+    Ok(cues)
+}
+
+// 4b. webvtt parser
+// header, optional cue ids, cue settings after the timing, NOTE/STYLE/REGION blocks
+pub fn parse_vtt(text: &str) -> SubtitleResult<Vec<CaptionCue>> {
+    let lines = split_lines(text);
+
+    if !lines.first().map(|l| l.starts_with("WEBVTT")).unwrap_or(false) {
+        return Err(parse_error(0, "missing WEBVTT header".to_string()));
+    }
+
+    // header runs until the first blank line
+    let mut i = lines.iter().position(|l| l.trim().is_empty()).unwrap_or(lines.len());
+    let mut cues = Vec::new();
+
+    while i < lines.len() {
+        let line = lines[i].trim();
+        if line.is_empty() {
+            i += 1;
+            continue;
+        }
+
+        // metadata blocks we dont care about
+        if line.starts_with("NOTE") || line == "STYLE" || line == "REGION" {
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                i += 1;
+            }
+            continue;
+        }
+
+        // cue identifier line
+        if !line.contains("-->") {
+            i += 1;
+            if i >= lines.len() || !lines[i].contains("-->") {
+                return Err(parse_error(i.min(lines.len() - 1), "expected a cue timing line".to_string()));
+            }
+        }
+
+        let (start, end) = parse_timing(lines[i], i)?;
+        i += 1;
+
+        let (cue_text, next) = collect_text(&lines, i);
+        i = next;
+
+        cues.push(CaptionCue { start, end, text: cue_text, words: Vec::new() });
+    }
+
+    Ok(cues)
+}
+
+// strips the bom and normalizes line endings
+fn split_lines(text: &str) -> Vec<&str> {
+    text.trim_start_matches('\u{feff}')
+        .lines()
+        .map(|l| l.trim_end_matches('\r'))
+        .collect()
+}
+
+// 5a. "00:00:01,000 --> 00:00:02,500" plus whatever trails it
+// idx is 0-based, errors report it 1-based like an editor would
+fn parse_timing(line: &str, idx: usize) -> SubtitleResult<(f64, f64)> {
+    let (left, right) = line
+        .split_once("-->")
+        .ok_or_else(|| parse_error(idx, format!("expected 'start --> end', got '{}'", line.trim())))?;
+
+    // vtt puts cue settings after the end time, srt sometimes puts coords there
+    let end_str = right.split_whitespace().next().unwrap_or("");
+
+    let start = parse_timestamp(left.trim())
+        .ok_or_else(|| parse_error(idx, format!("bad start time '{}'", left.trim())))?;
+    let end = parse_timestamp(end_str)
+        .ok_or_else(|| parse_error(idx, format!("bad end time '{}'", end_str)))?;
+
+    if end < start {
+        return Err(parse_error(idx, "cue ends before it starts".to_string()));
+    }
+
+    Ok((start, end))
+}
+
+// 5b. [hh:]mm:ss[,.]mmm to seconds
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let value = value.replace(',', ".");
+    let parts: Vec<&str> = value.split(':').collect();
+
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [h, m, s] => (h.parse::<u64>().ok()?, m.parse::<u64>().ok()?, *s),
+        [m, s] => (0, m.parse::<u64>().ok()?, *s),
+        _ => return None,
+    };
+
+    let seconds: f64 = seconds.parse().ok()?;
+    if minutes >= 60 || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+
+    Some(hours as f64 * 3600.0 + minutes as f64 * 60.0 + seconds)
+}
+
+// 5c. text lines up to the next blank line
+// markup like <i> or vtt voice/karaoke tags gets dropped, libass has its own styling
+fn collect_text(lines: &[&str], mut i: usize) -> (String, usize) {
+    let tags = Regex::new(r"<[^>]*>|\{[^}]*\}").unwrap();
+    let mut text_lines = Vec::new();
+
+    while i < lines.len() && !lines[i].trim().is_empty() {
+        text_lines.push(tags.replace_all(lines[i].trim(), "").to_string());
+        i += 1;
+    }
+
+    (text_lines.join("\n"), i)
+}
+
+fn parse_error(idx: usize, message: String) -> SubtitleError {
+    SubtitleError::Parse { line: idx + 1, message }
+}
+
+// 6a. tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srt() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i> there\r\nsecond line\r\n\r\n2\r\n00:01:00,250 --> 00:01:01,000\r\nbye\r\n";
+        let cues = parse_srt(srt).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!((cues[0].start, cues[0].end), (1.0, 2.5));
+        assert_eq!(cues[0].text, "Hello there\nsecond line");
+        assert_eq!((cues[1].start, cues[1].text.as_str()), (60.25, "bye"));
+    }
+
+    #[test]
+    fn test_parse_vtt() {
+        let vtt = "WEBVTT - exported\nKind: captions\n\nNOTE edited by hand\nsecond note line\n\nintro\n00:01.000 --> 00:02.000 align:start\n<v Sam>hey <00:01.500>you\n\n01:00:00.000 --> 01:00:01.000\nlate\n";
+        let cues = parse_vtt(vtt).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!((cues[0].start, cues[0].end), (1.0, 2.0));
+        assert_eq!(cues[0].text, "hey you");
+        assert_eq!(cues[1].start, 3600.0);
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_line() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nok\n\n2\n00:00:03,000 -> 00:00:04,000\nbroken\n";
+        let err = parse_srt(srt).unwrap_err().to_string();
+        assert_eq!(err, "subtitle parse error on line 6: expected 'start --> end', got '00:00:03,000 -> 00:00:04,000'");
+
+        let err = parse_vtt("1\n00:00.000 --> 00:01.000\n").unwrap_err().to_string();
+        assert!(err.contains("line 1"));
+
+        let err = parse_srt("1\n00:00:05,000 --> 00:00:0x,000\nhi\n").unwrap_err().to_string();
+        assert_eq!(err, "subtitle parse error on line 2: bad end time '00:00:0x,000'");
+    }
+}
@@ -4,6 +4,7 @@
 
 import { writable } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/tauri';
+import { readBinaryFile } from '@tauri-apps/api/fs';
 
 // 2a. dependency status - ffmpeg, yt-dlp, gpu encoder
 export const dependencies = writable({
@@ -12,6 +13,8 @@ export const dependencies = writable({
   allGood: false,
   checked: false,
   gpuEncoder: 'unknown',
+  ffmpegVersion: null,
+  missingFilters: [],  // features needing these (captions, text, mastering, sfx/music, transitions) get refused
 });
 
 // 2b. app directories
@@ -37,6 +40,8 @@ export async function checkDependencies() {
       allGood: status.all_good,
       checked: true,
       gpuEncoder: status.gpu_encoder || 'CPU',
+      ffmpegVersion: status.ffmpeg_version,
+      missingFilters: status.missing_filters || [],
     });
     return status;
   } catch (err) {
@@ -68,6 +73,31 @@ export async function validateYoutubeUrl(url) {
   }
 }
 
+// 3d. scan an sfx folder, returns every file with its category and the classifier's guess
+export async function scanSfxLibrary(folder) {
+  try {
+    return await invoke('scan_sfx_library', { folder });
+  } catch (err) {
+    console.error('failed to scan sfx library:', err);
+    return [];
+  }
+}
+
+// 3e. override (or exclude) one sfx file, null category + excluded false = back to auto
+export async function setSfxAssignment(folder, file, category = null, excluded = false) {
+  await invoke('set_sfx_assignment', { folder, file, category, excluded });
+}
+
+// 3f. sfx categories (built-in + user config), folder adds that library's own
+export async function getSfxCategories(folder = null) {
+  try {
+    return await invoke('get_sfx_categories', { folder });
+  } catch (err) {
+    console.error('failed to load sfx categories:', err);
+    return [];
+  }
+}
+
 // 4a. show a global error toast
 // auto-clears after duration (default 5s)
 export function showError(message, duration = 5000) {
@@ -84,3 +114,42 @@ export function showError(message, duration = 5000) {
 export function clearError() {
   globalError.set(null);
 }
+
+// 3g. dry run of the auto sfx placer, boundaries are optional cut times
+export async function previewSfxPlacement(userVideoPath, sfxFolder, options = {}) {
+  return await invoke('preview_sfx_placement', {
+    userVideoPath,
+    sfxFolder,
+    clipBoundaries: options.clipBoundaries || null,
+    clipLength: options.clipLength || null,
+    placement: options.placement || null,
+  });
+}
+
+// 3h. cue sheets - check one against the user video, or save placed sfx as one
+export async function validateSfxCues(path, userVideoPath, sfxFolder = null) {
+  return await invoke('validate_sfx_cues', { path, userVideoPath, sfxFolder });
+}
+
+export async function exportSfxCues(path, placements) {
+  return await invoke('export_sfx_cues', { path, placements });
+}
+
+// 3i. one frame of the layout for the settings panel, comes back as an object url
+// caller revokes the old url when a new one replaces it
+export async function renderLayoutStill(options) {
+  const path = await invoke('render_layout_still', {
+    userVideoPath: options.userVideoPath,
+    brollPath: options.brollPath,
+    timestamp: options.timestamp ?? 5,
+    outputFormat: options.outputFormat,
+    overlayPosition: options.overlayPosition || null,
+    splitRatio: options.splitRatio ?? null,
+    pipScale: options.pipScale ?? null,
+    pipStyle: options.pipStyle || null,
+    customWidth: options.customWidth || null,
+    customHeight: options.customHeight || null,
+  });
+  const bytes = await readBinaryFile(path);
+  return URL.createObjectURL(new Blob([bytes], { type: 'image/png' }));
+}
@@ -34,22 +34,45 @@ export async function updateJobs() {
   }
 }
 
+// everything the backend needs to build a job
+// start_job and render_preview take the same request
+function jobRequest(config) {
+  return {
+    youtubeLinks: config.youtubeLinks || [],
+    localBrollPaths: config.localBrollPaths || null,
+    userVideoPath: config.userVideoPath,
+    outputFormat: config.outputFormat,
+    outputFormats: config.outputFormats || null,
+    overlayPosition: config.overlayPosition || 'top',
+    customWidth: config.customWidth || null,
+    customHeight: config.customHeight || null,
+    splitRatio: config.splitRatio || null,
+    pipScale: config.pipScale || null,
+    sfxFolder: config.sfxFolder || null,
+    autoReframe: config.autoReframe || null,
+    pipStyle: config.pipStyle || null,
+    captions: config.captions || null,
+    subtitlesPath: config.subtitlesPath || null,
+    subtitleStyle: config.subtitleStyle || null,
+    title: config.title || null,
+    textOverlays: config.textOverlays || null,
+    watermark: config.watermark || null,
+    bumpers: config.bumpers || null,
+    mastering: config.mastering || null,
+    music: config.music || null,
+    sfxPlacement: config.sfxPlacement || null,
+    sfxCues: config.sfxCues || null,
+    encoder: config.encoder || null,
+    useProxies: config.useProxies || null,
+    frameAccurate: config.frameAccurate || null,
+  };
+}
+
 // 4b. start a new job with all the settings
 // this is the main function users trigger
 export async function startJob(config) {
   try {
-    const jobId = await invoke('start_job', {
-      youtubeLinks: config.youtubeLinks || [],
-      localBrollPaths: config.localBrollPaths || null,
-      userVideoPath: config.userVideoPath,
-      outputFormat: config.outputFormat,
-      overlayPosition: config.overlayPosition || 'top',
-      customWidth: config.customWidth || null,
-      customHeight: config.customHeight || null,
-      splitRatio: config.splitRatio || null,
-      pipScale: config.pipScale || null,
-      sfxFolder: config.sfxFolder || null,
-    });
+    const jobId = await invoke('start_job', { request: jobRequest(config) });
     
     // refresh jobs list right away
     await updateJobs();
@@ -82,3 +105,15 @@ export async function getJobStatus(jobId) {
     return null;
   }
 }
+
+// 4e. render a short low res preview of the layout
+// resolves once its done, doesnt show up in the jobs list
+export async function renderPreview(config, seconds = 10) {
+  try {
+    const path = await invoke('render_preview', { request: jobRequest(config), seconds });
+    return { success: true, path };
+  } catch (err) {
+    console.error('failed to render preview:', err);
+    return { success: false, error: err };
+  }
+}